@@ -0,0 +1,320 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+// Reference:
+//   - https://en.wikipedia.org/wiki/Approximate_string_matching (Sellers algorithm)
+//   - https://laurikari.net/tre/documentation/agrep/
+
+use std::collections::HashSet;
+use std::io;
+use std::path::PathBuf;
+
+use grep_matcher::{Match, Matcher, NoCaptures, NoError};
+use grep_searcher::{Searcher, Sink, SinkMatch};
+
+use hmglib::Homoglyphs;
+
+use super::common::{char_indices_lossy, find_in_lines};
+use super::matcher::HmgMatcher;
+use super::{Config, OutputMode, SortMode};
+
+/// Costs of each edit operation for approximate matching.
+#[derive(Clone, Copy, Debug)]
+pub struct ApproxCosts {
+    /// Cost of a pattern character missing from the text.
+    pub delete: u32,
+
+    /// Cost of an extra character in the text.
+    pub insert: u32,
+
+    /// Cost of a wrong character.
+    pub substitute: u32,
+
+    /// Cost of a homoglyph of the pattern character.
+    pub homoglyph: u32,
+}
+
+impl Default for ApproxCosts {
+    fn default() -> Self {
+        ApproxCosts {
+            delete: 1,
+            insert: 1,
+            substitute: 1,
+            homoglyph: 0,
+        }
+    }
+}
+
+/// A character of the pattern, with the characters that impersonate it.
+#[derive(Clone, Debug)]
+struct Position {
+    original: char,
+    homoglyphs: HashSet<char>,
+}
+
+/// An approximate match, with the error count (total cost) of the match.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ApproxMatch {
+    pub start: usize,
+    pub end: usize,
+    pub errors: u32,
+}
+
+/// Matcher for approximate (edit-distance) matching, where a homoglyph substitution
+/// costs `ApproxCosts::homoglyph` instead of `ApproxCosts::substitute`.
+#[derive(Clone, Debug)]
+pub struct ApproxMatcher {
+    positions: Vec<Position>,
+    costs: ApproxCosts,
+    max_errors: u32,
+    case_insensitive: bool,
+}
+
+impl ApproxMatcher {
+    pub fn new(homoglyphs: &Homoglyphs, plane: &str, costs: ApproxCosts) -> Self {
+        let pattern = homoglyphs.get_alternatives(plane);
+        let originals: Vec<char> = plane.chars().collect();
+
+        let positions = pattern
+            .iter()
+            .enumerate()
+            .map(|(i, alternatives)| {
+                let chars: HashSet<char> = alternatives
+                    .iter()
+                    .filter_map(|a| {
+                        let mut chars = a.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => Some(c),
+                            _ => None,
+                        }
+                    })
+                    .collect();
+
+                // if the pattern and positions are not aligned, all alternatives are homoglyphs.
+                let original = match originals.get(i) {
+                    Some(c) if originals.len() == pattern.len() => *c,
+                    _ => '\u{fffd}',
+                };
+
+                Position {
+                    original,
+                    homoglyphs: chars,
+                }
+            })
+            .collect();
+
+        ApproxMatcher {
+            positions,
+            costs,
+            max_errors: 1,
+            case_insensitive: false,
+        }
+    }
+
+    pub fn max_errors(&mut self, num: u32) -> &mut Self {
+        self.max_errors = num;
+        self
+    }
+
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// The highest cost a match of this pattern can have. Used as "unlimited" errors.
+    pub fn worst_errors(&self) -> u32 {
+        let per_char = self.costs.delete.max(self.costs.substitute);
+        per_char * self.positions.len() as u32
+    }
+
+    /// Cost of matching text char `c` at pattern position `p`.
+    fn substitute_cost(&self, p: &Position, c: char) -> u32 {
+        if p.original == c || (self.case_insensitive && eq_ignore_case(p.original, c)) {
+            return 0;
+        }
+
+        let is_homoglyph = p.homoglyphs.contains(&c)
+            || (self.case_insensitive && p.homoglyphs.iter().any(|h| eq_ignore_case(*h, c)));
+        if is_homoglyph {
+            self.costs.homoglyph
+        } else {
+            self.costs.substitute
+        }
+    }
+
+    /// Find all non-overlapping approximate matches in `line` that have at most `max_errors` errors.
+    pub fn find_all(&self, line: &[u8]) -> Vec<ApproxMatch> {
+        let mut chars = char_indices_lossy(line);
+        while let Some((_, _, '\n')) | Some((_, _, '\r')) = chars.last() {
+            chars.pop();
+        }
+        let n = chars.len();
+
+        // cost and start (in chars) of the best match of the whole pattern ending at each char.
+        // Sellers algorithm: a match can start at any char, so the first row is zero.
+        let mut costs: Vec<u32> = vec![0; n + 1];
+        let mut starts: Vec<usize> = (0..=n).collect();
+
+        for p in &self.positions {
+            let mut next_costs = vec![costs[0] + self.costs.delete; n + 1];
+            let mut next_starts = vec![starts[0]; n + 1];
+
+            for j in 1..=n {
+                let candidates = [
+                    (costs[j - 1] + self.substitute_cost(p, chars[j - 1].2), starts[j - 1]),
+                    (costs[j] + self.costs.delete, starts[j]),
+                    (next_costs[j - 1] + self.costs.insert, next_starts[j - 1]),
+                ];
+
+                let best = candidates.iter().min_by_key(|(c, _)| *c).unwrap();
+                next_costs[j] = best.0;
+                next_starts[j] = best.1;
+            }
+
+            costs = next_costs;
+            starts = next_starts;
+        }
+
+        let mut result = vec![];
+        let mut last_end = 0;
+        let mut j = 1;
+        while j <= n {
+            if costs[j] > self.max_errors || starts[j] < last_end || starts[j] == j {
+                j += 1;
+                continue;
+            }
+
+            // extend the match while the cost decreases.
+            while j < n && costs[j + 1] < costs[j] {
+                j += 1;
+            }
+
+            result.push(ApproxMatch {
+                start: chars[starts[j]].0,
+                end: chars[j - 1].1,
+                errors: costs[j],
+            });
+            last_end = j;
+            j += 1;
+        }
+
+        result
+    }
+
+    /// Get the lowest error count in `line`, if it has a match.
+    pub fn min_errors(&self, line: &[u8]) -> Option<u32> {
+        self.find_all(line).iter().map(|m| m.errors).min()
+    }
+}
+
+impl Matcher for ApproxMatcher {
+    type Captures = NoCaptures;
+    type Error = NoError;
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, NoError> {
+        let result = find_in_lines(haystack, at, |line| {
+            self.find_all(line).first().map(|m| (m.start, m.end))
+        });
+
+        Ok(result.map(|(s, e)| Match::new(s, e)))
+    }
+
+    fn new_captures(&self) -> Result<NoCaptures, NoError> {
+        Ok(NoCaptures::new())
+    }
+}
+
+/// Sink to get the lowest error count of the matching lines.
+pub struct BestErrorsSink<'a> {
+    matcher: &'a HmgMatcher,
+    best_errors: Option<u32>,
+    has_matched: bool,
+}
+
+impl<'a> BestErrorsSink<'a> {
+    pub fn new(matcher: &'a HmgMatcher) -> Self {
+        BestErrorsSink {
+            matcher,
+            best_errors: None,
+            has_matched: false,
+        }
+    }
+
+    /// The lowest error count of the matches, if any line has matched.
+    pub fn best_errors(&self) -> Option<u32> {
+        self.best_errors
+    }
+
+    pub fn has_matched(&self) -> bool {
+        self.has_matched
+    }
+}
+
+impl<'a> Sink for BestErrorsSink<'a> {
+    type Error = io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch) -> Result<bool, io::Error> {
+        self.has_matched = true;
+
+        let errors = self.matcher.find_all(mat.bytes()).iter().filter_map(|m| m.errors).min();
+        if let Some(errors) = errors {
+            self.best_errors = Some(self.best_errors.map_or(errors, |best| best.min(errors)));
+        }
+
+        Ok(true)
+    }
+}
+
+/// Get the lowest error count of all lines in `path_list`.
+/// Used by `--best-match` to select only the lowest-cost lines. The files are searched
+/// like the second pass, so the archives, the documents and the filters are the same.
+pub fn best_errors(config: Config, matcher: &HmgMatcher, path_list: Vec<PathBuf>) -> Option<u32> {
+    let mut config = config;
+    config.output_mode(OutputMode::BestErrors).sort(SortMode::None);
+
+    super::scan(config, matcher.clone(), path_list).best_errors
+}
+
+fn eq_ignore_case(a: char, b: char) -> bool {
+    a.to_lowercase().eq(b.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_approx_homoglyph_cost() {
+        let mut matcher = ApproxMatcher::new(&Homoglyphs::new(), "ちんこ", ApproxCosts::default());
+        matcher.max_errors(0);
+
+        // homoglyph substitution is free by default.
+        let matches = matcher.find_all("xㄘんこx".as_bytes());
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].errors, 0);
+        assert_eq!(&"xㄘんこx"[matches[0].start..matches[0].end], "ㄘんこ");
+
+        // one deleted character.
+        assert!(matcher.find_all("ちこ".as_bytes()).is_empty());
+        matcher.max_errors(1);
+        assert_eq!(matcher.min_errors("ちこ".as_bytes()), Some(1));
+    }
+
+    #[test]
+    fn tests_best_errors_sink() {
+        let mut approx = ApproxMatcher::new(&Homoglyphs::new(), "ちんこ", ApproxCosts::default());
+        approx.max_errors(1);
+        let matcher = HmgMatcher::Approx(approx);
+
+        // the searcher has no line numbers, like hmgrep without `-n`.
+        let mut searcher = grep_searcher::SearcherBuilder::new().line_number(false).build();
+        let mut sink = BestErrorsSink::new(&matcher);
+        searcher
+            .search_slice(&matcher, "ちこ\nfoo\nㄘんこ\n".as_bytes(), &mut sink)
+            .unwrap();
+
+        assert!(sink.has_matched());
+        assert_eq!(sink.best_errors(), Some(0));
+    }
+}
@@ -0,0 +1,235 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek};
+use std::path::Path;
+
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use super::decompress::{self, Compression};
+
+/// A format of archive whose members are searched with `-z/--search-zip`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArchiveKind {
+    Zip,
+
+    /// A tar archive, and the compression of the whole archive (e.g. `.tar.gz`).
+    Tar(Option<Compression>),
+}
+
+impl ArchiveKind {
+    /// Get the format of `path` by its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        let (stem, extension) = match name.rfind('.') {
+            Some(i) => (&name[..i], &name[i + 1..]),
+            None => return None,
+        };
+
+        match extension {
+            "zip" => Some(ArchiveKind::Zip),
+            "tar" => Some(ArchiveKind::Tar(None)),
+            "tgz" => Some(ArchiveKind::Tar(Some(Compression::Gzip))),
+            "txz" => Some(ArchiveKind::Tar(Some(Compression::Xz))),
+            "tbz" | "tbz2" => Some(ArchiveKind::Tar(Some(Compression::Bzip2))),
+            "tzst" => Some(ArchiveKind::Tar(Some(Compression::Zstd))),
+            _ if stem.ends_with(".tar") => Compression::from_path(path).map(|c| ArchiveKind::Tar(Some(c))),
+            _ => None,
+        }
+    }
+}
+
+/// The limits of reading an archive, against archive bombs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    /// The maximum size of a member after decompression. Larger members are skipped.
+    pub max_member_size: Option<u64>,
+
+    /// The maximum depth of the archives in an archive. With 0, the archives in an archive are skipped.
+    pub max_depth: usize,
+}
+
+/// Read the file members of the archive at `path`, and call `visit` with the name and the data of each.
+/// A member is named like `archive.zip!path/in/archive`. `visit` returns false to stop reading.
+pub fn read_members<F>(path: &Path, kind: ArchiveKind, limits: &Limits, visit: &mut F) -> io::Result<()>
+where
+    F: FnMut(&Path, &[u8]) -> bool,
+{
+    let file = File::open(path)?;
+    read_archive(&path.to_string_lossy(), kind, file, limits, 0, visit)?;
+    Ok(())
+}
+
+/// Read the members of an archive named `name`. Returns false if `visit` stopped reading.
+fn read_archive<R, F>(name: &str, kind: ArchiveKind, rdr: R, limits: &Limits, depth: usize, visit: &mut F) -> io::Result<bool>
+where
+    R: Read + Seek + 'static,
+    F: FnMut(&Path, &[u8]) -> bool,
+{
+    match kind {
+        ArchiveKind::Zip => {
+            let mut archive = ZipArchive::new(rdr).map_err(zip_to_io_error)?;
+
+            for i in 0..archive.len() {
+                let (member, data) = {
+                    let mut file = match archive.by_index(i) {
+                        Ok(file) => file,
+                        // encrypted members, and the compressions that are not built in.
+                        Err(ZipError::UnsupportedArchive(_)) => continue,
+                        Err(e) => return Err(zip_to_io_error(e)),
+                    };
+                    if file.is_dir() {
+                        continue;
+                    }
+
+                    let member = format!("{}!{}", name, file.name());
+                    let size = file.size();
+                    (member, read_member(&mut file, size, limits)?)
+                };
+
+                if let Some(data) = data {
+                    if !visit_member(&member, data, limits, depth, visit)? {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+
+        ArchiveKind::Tar(compression) => {
+            let rdr: Box<dyn Read> = match compression {
+                Some(compression) => compression.decoder(rdr, None)?,
+                None => Box::new(rdr),
+            };
+            let mut archive = tar::Archive::new(rdr);
+
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+
+                let member = format!("{}!{}", name, entry.path()?.to_string_lossy());
+                let size = entry.header().size()?;
+
+                if let Some(data) = read_member(&mut entry, size, limits)? {
+                    if !visit_member(&member, data, limits, depth, visit)? {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+/// Descend into `data` if it is an archive, or decompress it if it is compressed, then visit it.
+fn visit_member<F>(member: &str, data: Vec<u8>, limits: &Limits, depth: usize, visit: &mut F) -> io::Result<bool>
+where
+    F: FnMut(&Path, &[u8]) -> bool,
+{
+    let path = Path::new(member);
+
+    if let Some(kind) = ArchiveKind::from_path(path) {
+        if depth < limits.max_depth {
+            return read_archive(member, kind, Cursor::new(data), limits, depth + 1, visit);
+        }
+        return Ok(true);
+    }
+
+    if let Some(compression) = Compression::from_path(path) {
+        // a member over `max_member_size` is skipped, not an error.
+        let limit = limits.max_member_size.map(|l| l + 1);
+        let data = compression
+            .decoder(Cursor::new(data), limit)
+            .and_then(|mut decoder| read_member(&mut decoder, 0, limits));
+        return match data {
+            Ok(Some(data)) => Ok(visit(path, &data)),
+            Ok(None) => Ok(true),
+            Err(ref e) if decompress::is_limit_error(e) => Ok(true),
+            Err(e) => Err(e),
+        };
+    }
+
+    Ok(visit(path, &data))
+}
+
+/// Read a member of `size` bytes. Returns None if it is larger than `max_member_size`.
+/// The size in the header is not trusted, so at most `max_member_size` bytes are read.
+fn read_member<R: Read>(rdr: &mut R, size: u64, limits: &Limits) -> io::Result<Option<Vec<u8>>> {
+    let mut data = vec![];
+
+    match limits.max_member_size {
+        Some(limit) if size > limit => return Ok(None),
+        Some(limit) => {
+            rdr.take(limit + 1).read_to_end(&mut data)?;
+            if data.len() as u64 > limit {
+                return Ok(None);
+            }
+        }
+        None => {
+            rdr.read_to_end(&mut data)?;
+        }
+    }
+
+    Ok(Some(data))
+}
+
+fn zip_to_io_error(e: ZipError) -> io::Error {
+    match e {
+        ZipError::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    #[test]
+    fn tests_read_zip_members() {
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("a.tar.gz")),
+            Some(ArchiveKind::Tar(Some(Compression::Gzip)))
+        );
+        assert_eq!(ArchiveKind::from_path(Path::new("a.gz")), None);
+
+        // a.zip contains `dir/a.txt`, `big.txt` over the limit, and `inner.zip` that contains `b.txt`.
+        let build_zip = |files: &[(&str, &[u8])]| {
+            let mut writer = ZipWriter::new(Cursor::new(vec![]));
+            for (name, data) in files {
+                writer.start_file(*name, FileOptions::default()).unwrap();
+                writer.write_all(data).unwrap();
+            }
+            writer.finish().unwrap().into_inner()
+        };
+        let inner = build_zip(&[("b.txt", b"b")]);
+        let big = vec![b'x'; 1024];
+        let outer = build_zip(&[("dir/a.txt", b"a"), ("big.txt", &big), ("inner.zip", &inner)]);
+
+        let read = |max_depth| {
+            let limits = Limits {
+                max_member_size: Some(512),
+                max_depth,
+            };
+            let mut names = vec![];
+            read_archive("a.zip", ArchiveKind::Zip, Cursor::new(outer.clone()), &limits, 0, &mut |path, _| {
+                names.push(path.to_string_lossy().into_owned());
+                true
+            })
+            .unwrap();
+            names
+        };
+
+        assert_eq!(read(0), vec!["a.zip!dir/a.txt"]);
+        assert_eq!(read(1), vec!["a.zip!dir/a.txt", "a.zip!inner.zip!b.txt"]);
+    }
+}
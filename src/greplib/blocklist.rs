@@ -0,0 +1,119 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+use std::sync::Arc;
+
+use aho_corasick::AhoCorasick;
+use grep_matcher::{Match, Matcher, NoCaptures, NoError};
+
+use hmglib::Homoglyphs;
+
+use super::common::find_in_lines;
+use super::skeleton::SkeletonText;
+
+/// A hit of a blocklist entry in a line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlocklistMatch {
+    /// The index of the entry in `Blocklist::entries`.
+    pub entry: usize,
+
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Matcher for a large list of words.
+/// The skeleton of every entry is compiled into a single Aho-Corasick automaton,
+/// so the cost of a search does not grow with the number of entries.
+///
+/// # Example
+///
+/// ```
+/// use hmg_utils::greplib::blocklist::Blocklist;
+/// use hmg_utils::hmglib::Homoglyphs;
+///
+/// let entries = vec!["ちんこ".to_string(), "paypal".to_string()];
+/// let blocklist = Blocklist::new(&Homoglyphs::new(), entries);
+///
+/// let hits = blocklist.find_all("ㄘんこ".as_bytes());
+/// assert_eq!(blocklist.entry(hits[0].entry), Some("ちんこ"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Blocklist {
+    homoglyphs: Homoglyphs,
+    entries: Arc<Vec<String>>,
+
+    /// The automaton of the entry skeletons, and the entry index of each automaton pattern.
+    automaton: Arc<AhoCorasick>,
+    pattern_entries: Arc<Vec<usize>>,
+}
+
+impl Blocklist {
+    pub fn new(homoglyphs: &Homoglyphs, entries: Vec<String>) -> Self {
+        let mut skeletons = vec![];
+        let mut pattern_entries = vec![];
+
+        // an empty skeleton would match everywhere, so skip it.
+        for (i, entry) in entries.iter().enumerate() {
+            let skeleton = homoglyphs.get_skeleton(entry);
+            if !skeleton.is_empty() {
+                skeletons.push(skeleton);
+                pattern_entries.push(i);
+            }
+        }
+
+        let automaton = AhoCorasick::new(&skeletons).unwrap();
+
+        Blocklist {
+            homoglyphs: homoglyphs.clone(),
+            entries: Arc::new(entries),
+            automaton: Arc::new(automaton),
+            pattern_entries: Arc::new(pattern_entries),
+        }
+    }
+
+    /// Get the entry text of `BlocklistMatch::entry`.
+    pub fn entry(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(|e| e.as_str())
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Find the hits of every entry in `line`, including overlapped ones.
+    pub fn find_all(&self, line: &[u8]) -> Vec<BlocklistMatch> {
+        let skeleton = SkeletonText::new(&self.homoglyphs, line);
+
+        let mut result = vec![];
+        for m in self.automaton.find_overlapping_iter(&skeleton.text) {
+            if let Some((start, end)) = skeleton.to_original_aligned(m.start(), m.end()) {
+                result.push(BlocklistMatch {
+                    entry: self.pattern_entries[m.pattern().as_usize()],
+                    start,
+                    end,
+                });
+            }
+        }
+
+        result.sort_by_key(|m| (m.start, usize::MAX - m.end, m.entry));
+        result
+    }
+}
+
+impl Matcher for Blocklist {
+    type Captures = NoCaptures;
+    type Error = NoError;
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, NoError> {
+        let result = find_in_lines(haystack, at, |line| {
+            self.find_all(line).first().map(|m| (m.start, m.end))
+        });
+
+        Ok(result.map(|(s, e)| Match::new(s, e)))
+    }
+
+    fn new_captures(&self) -> Result<NoCaptures, NoError> {
+        Ok(NoCaptures::new())
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+// Reference:
+//   - https://www.gnu.org/software/grep/manual/html_node/Environment-Variables.html (GREP_COLORS)
+//   - https://en.wikipedia.org/wiki/ANSI_escape_code#SGR_(Select_Graphic_Rendition)_parameters
+
+use std::error::Error;
+
+use grep::printer::{default_color_specs, ColorSpecs, UserColorSpec};
+use termcolor::ColorSpec;
+
+const COLOR_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// The default color of the substituted characters in a match.
+const SUBSTITUTION_SPECS: [&str; 3] = ["fg:yellow", "style:bold", "style:underline"];
+
+/// The colors of the printers.
+#[derive(Clone, Debug)]
+pub struct Colors {
+    /// The colors of path, line number and match.
+    pub specs: ColorSpecs,
+
+    /// The color of the characters of a match that differ from the pattern.
+    pub substitution: ColorSpec,
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        build_colors(None, &[]).unwrap()
+    }
+}
+
+/// Build the colors of the printers.
+/// `grep_colors` is the value of `GREP_COLORS`, and `user_specs` are specs in the
+/// format of `grep_printer::UserColorSpec` (e.g. `match:fg:blue`). Later specs win.
+/// The type `substitution` (e.g. `substitution:fg:cyan`) sets the color of the substituted characters.
+pub fn build_colors(grep_colors: Option<&str>, user_specs: &[&str]) -> Result<Colors, Box<dyn Error>> {
+    let mut specs = default_color_specs();
+    let mut substitution_specs = vec![];
+
+    for spec in SUBSTITUTION_SPECS.iter() {
+        substitution_specs.push(format!("match:{}", spec).parse::<UserColorSpec>()?);
+    }
+
+    if let Some(value) = grep_colors {
+        for spec in grep_colors_to_specs(value) {
+            specs.push(spec.parse::<UserColorSpec>()?);
+        }
+    }
+
+    for spec in user_specs {
+        // `UserColorSpec` does not know the type `substitution`, so parse it as a match.
+        match spec.strip_prefix("substitution:") {
+            Some(rest) => substitution_specs.push(format!("match:{}", rest).parse::<UserColorSpec>()?),
+            None => specs.push(spec.parse::<UserColorSpec>()?),
+        }
+    }
+
+    Ok(Colors {
+        specs: ColorSpecs::new(&specs),
+        substitution: ColorSpecs::new(&substitution_specs).matched().clone(),
+    })
+}
+
+/// Convert `GREP_COLORS` (e.g. `ms=01;31:fn=35:ln=32`) to the specs of `UserColorSpec`.
+/// Only `ms`/`mt` (match), `fn` (path) and `ln` (line number) are supported; other keys are ignored.
+pub fn grep_colors_to_specs(value: &str) -> Vec<String> {
+    let mut result = vec![];
+
+    for capability in value.split(':') {
+        let (key, sgr) = match capability.find('=') {
+            Some(i) => (&capability[..i], &capability[i + 1..]),
+            None => continue,
+        };
+
+        let out_type = match key {
+            "ms" | "mt" => "match",
+            "fn" => "path",
+            "ln" => "line",
+            _ => continue,
+        };
+
+        // the capability replaces the default color.
+        result.push(format!("{}:none", out_type));
+        for (attribute, value) in sgr_to_attributes(sgr) {
+            result.push(format!("{}:{}:{}", out_type, attribute, value));
+        }
+    }
+
+    result
+}
+
+/// Convert SGR parameters (e.g. `01;38;5;208`) to pairs of `UserColorSpec` attribute and value.
+fn sgr_to_attributes(sgr: &str) -> Vec<(&'static str, String)> {
+    let codes: Vec<u32> = sgr.split(';').filter_map(|c| c.parse().ok()).collect();
+
+    let mut result = vec![];
+    let mut i = 0;
+    while i < codes.len() {
+        match codes[i] {
+            1 => result.push(("style", "bold".to_string())),
+            4 => result.push(("style", "underline".to_string())),
+            22 => result.push(("style", "nobold".to_string())),
+            24 => result.push(("style", "nounderline".to_string())),
+            c @ 30..=37 => result.push(("fg", COLOR_NAMES[(c - 30) as usize].to_string())),
+            c @ 40..=47 => result.push(("bg", COLOR_NAMES[(c - 40) as usize].to_string())),
+            c @ 90..=97 => {
+                result.push(("fg", COLOR_NAMES[(c - 90) as usize].to_string()));
+                result.push(("style", "intense".to_string()));
+            }
+            c @ 100..=107 => result.push(("bg", COLOR_NAMES[(c - 100) as usize].to_string())),
+
+            // 256 colors (`38;5;N`) and true colors (`38;2;R;G;B`).
+            c @ 38 | c @ 48 => {
+                let attribute = if c == 38 { "fg" } else { "bg" };
+                match codes.get(i + 1) {
+                    Some(5) if i + 2 < codes.len() => {
+                        result.push((attribute, codes[i + 2].to_string()));
+                        i += 2;
+                    }
+                    Some(2) if i + 4 < codes.len() => {
+                        let rgb = format!("{},{},{}", codes[i + 2], codes[i + 3], codes[i + 4]);
+                        result.push((attribute, rgb));
+                        i += 4;
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_grep_colors_to_specs() {
+        let specs = grep_colors_to_specs("ms=01;32:fn=38;5;208:sl=:ln=4;94");
+        assert_eq!(
+            specs,
+            vec![
+                "match:none",
+                "match:style:bold",
+                "match:fg:green",
+                "path:none",
+                "path:fg:208",
+                "line:none",
+                "line:style:underline",
+                "line:fg:blue",
+                "line:style:intense",
+            ]
+        );
+
+        assert!(build_colors(Some("ms=01;32"), &["path:fg:blue", "substitution:none"]).is_ok());
+        assert!(build_colors(None, &["path:fg:nocolor"]).is_err());
+    }
+}
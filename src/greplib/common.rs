@@ -0,0 +1,114 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+use grep_matcher::Matcher;
+use grep_searcher::{Searcher, SinkMatch};
+use memchr::{memchr, memrchr};
+
+/// Decode `bytes` as UTF-8 into `(start, end, char)` tuples.
+/// Invalid bytes are decoded one by one as `U+FFFD`, so the offsets always
+/// point back into the original bytes.
+pub fn char_indices_lossy(bytes: &[u8]) -> Vec<(usize, usize, char)> {
+    let mut result = vec![];
+
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let rest = &bytes[offset..];
+        let valid = match std::str::from_utf8(rest) {
+            Ok(s) => s,
+            Err(e) => std::str::from_utf8(&rest[..e.valid_up_to()]).unwrap(),
+        };
+
+        for (i, c) in valid.char_indices() {
+            result.push((offset + i, offset + i + c.len_utf8(), c));
+        }
+        offset += valid.len();
+
+        // skip one invalid byte.
+        if offset < bytes.len() {
+            result.push((offset, offset + 1, '\u{fffd}'));
+            offset += 1;
+        }
+    }
+
+    result
+}
+
+/// Call `find` with each line of `haystack` from `at`, and return the first match.
+/// `find` returns the range of the match in the given line.
+pub fn find_in_lines<F>(haystack: &[u8], at: usize, find: F) -> Option<(usize, usize)>
+where
+    F: Fn(&[u8]) -> Option<(usize, usize)>,
+{
+    let mut line_start = at;
+    while line_start <= haystack.len() {
+        let line_end = match memchr(b'\n', &haystack[line_start..]) {
+            Some(i) => line_start + i + 1,
+            None => haystack.len(),
+        };
+
+        if let Some((s, e)) = find(&haystack[line_start..line_end]) {
+            return Some((line_start + s, line_start + e));
+        }
+
+        if line_end == haystack.len() {
+            break;
+        }
+        line_start = line_end;
+    }
+
+    None
+}
+
+/// Like `find_in_lines`, but each line is a whole record, such as a JSON line, so `find` gets
+/// the line from its start, with the offset in the line from which a match can start.
+pub fn find_in_records<F>(haystack: &[u8], at: usize, find: F) -> Option<(usize, usize)>
+where
+    F: Fn(&[u8], usize) -> Option<(usize, usize)>,
+{
+    find_in_records_by(haystack, at, b'\n', find)
+}
+
+/// Like `find_in_records`, but the records end with `terminator`, such as NUL.
+pub fn find_in_records_by<F>(haystack: &[u8], at: usize, terminator: u8, find: F) -> Option<(usize, usize)>
+where
+    F: Fn(&[u8], usize) -> Option<(usize, usize)>,
+{
+    let mut line_start = memrchr(terminator, &haystack[..at]).map_or(0, |i| i + 1);
+
+    while line_start < haystack.len() {
+        let line_end = match memchr(terminator, &haystack[line_start..]) {
+            Some(i) => line_start + i + 1,
+            None => haystack.len(),
+        };
+
+        let min_start = at.saturating_sub(line_start);
+        if let Some((s, e)) = find(&haystack[line_start..line_end], min_start) {
+            return Some((line_start + s, line_start + e));
+        }
+
+        line_start = line_end;
+    }
+
+    None
+}
+
+/// Split the bytes of `mat` to the units printed as a line.
+/// A match across lines (`-U`) can not be split, so it is a single unit.
+pub fn match_lines<'b, M: Matcher>(searcher: &Searcher, matcher: M, mat: &SinkMatch<'b>) -> Vec<&'b [u8]> {
+    if searcher.multi_line_with_matcher(matcher) {
+        vec![mat.bytes()]
+    } else {
+        mat.lines().collect()
+    }
+}
+
+/// Remove the terminator of a record of `--null-data` or `-d`, to print it as a line.
+pub fn strip_terminator<'b>(searcher: &Searcher, line: &'b [u8]) -> &'b [u8] {
+    let terminator = searcher.line_terminator().as_byte();
+    match line.last() {
+        Some(b) if *b == terminator && terminator != b'\n' => &line[..line.len() - 1],
+        _ => line,
+    }
+}
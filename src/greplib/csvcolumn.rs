@@ -0,0 +1,327 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+// Reference:
+//   - https://www.rfc-editor.org/rfc/rfc4180 (CSV)
+
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use csv::{ByteRecord, ReaderBuilder};
+use grep_matcher::{Match, Matcher, NoCaptures, NoError};
+
+use super::common::find_in_records_by;
+use super::matcher::{HmgMatch, HmgMatcher};
+
+/// A column given by `--column`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Column {
+    /// A 1-based index.
+    Index(usize),
+
+    /// A name in the header.
+    Name(String),
+}
+
+impl Column {
+    /// Parse a 1-based index, or a name.
+    pub fn parse(text: &str) -> Self {
+        match text.parse::<usize>() {
+            Ok(i) if i > 0 => Column::Index(i),
+            _ => Column::Name(text.to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ColumnError(String);
+
+impl fmt::Display for ColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no column in the header: {}", self.0)
+    }
+}
+
+impl Error for ColumnError {}
+
+/// The terminator of the records given to `CsvColumnMatcher`, because a quoted cell can have line breaks.
+pub const RECORD_TERMINATOR: u8 = b'\0';
+
+/// The records of CSV data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Records {
+    /// The (start, end) offsets of each record in the data, without the line break after it.
+    pub ranges: Vec<(usize, usize)>,
+
+    /// The first record, to resolve the column names.
+    pub header: Vec<String>,
+}
+
+/// Split CSV `data` to the records, so that a record number is a row number.
+pub fn records(data: &[u8], delimiter: u8) -> Result<Records, csv::Error> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(data);
+
+    let mut result = vec![];
+    let mut header = None;
+    let mut record = ByteRecord::new();
+    while reader.read_byte_record(&mut record)? {
+        if header.is_none() {
+            header = Some(record.iter().map(|c| String::from_utf8_lossy(c).into_owned()).collect());
+        }
+
+        // the position of a record after `\r\n` is at `\n`, and the empty lines are not records.
+        let mut start = record.position().map_or(0, |p| p.byte() as usize);
+        let end = reader.position().byte() as usize;
+        while start < end && (data[start] == b'\r' || data[start] == b'\n') {
+            start += 1;
+        }
+        let line = &data[start..end];
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        result.push((start, start + line.len()));
+    }
+
+    Ok(Records {
+        ranges: result,
+        header: header.unwrap_or_default(),
+    })
+}
+
+/// Matcher that searches only the given columns of each record of CSV, terminated by `RECORD_TERMINATOR`.
+/// The matches are labeled with the column that matched.
+#[derive(Clone, Debug)]
+pub struct CsvColumnMatcher {
+    matcher: Arc<HmgMatcher>,
+    columns: Vec<Column>,
+
+    /// Whether the first record is the header. Without the header, only indexes can be used.
+    has_header: bool,
+
+    /// Whether the input is TSV, regardless of the file name.
+    is_tsv: bool,
+
+    /// The delimiter and the 0-based indexes of the columns of the current file.
+    /// Set by `for_file`. None of `indexes` means all columns.
+    delimiter: u8,
+    indexes: Option<Vec<usize>>,
+    labels: Vec<String>,
+}
+
+impl CsvColumnMatcher {
+    /// If `columns` is empty, all columns are searched.
+    pub fn new(matcher: HmgMatcher, columns: Vec<Column>, has_header: bool, is_tsv: bool) -> Self {
+        CsvColumnMatcher {
+            matcher: Arc::new(matcher),
+            columns,
+            has_header,
+            is_tsv,
+            delimiter: b',',
+            indexes: None,
+            labels: vec![],
+        }
+    }
+
+    /// The matcher of the cells.
+    pub fn inner(&self) -> &HmgMatcher {
+        &self.matcher
+    }
+
+    pub fn inner_mut(&mut self) -> &mut HmgMatcher {
+        Arc::make_mut(&mut self.matcher)
+    }
+
+    /// The delimiter of `path`. `*.tsv` and `*.tab` are separated by tabs.
+    pub fn delimiter_of(&self, path: &Path) -> u8 {
+        let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            _ if self.is_tsv => b'\t',
+            Some("tsv") | Some("tab") => b'\t',
+            _ => b',',
+        }
+    }
+
+    /// Get the matcher of a file, with the columns resolved by `header`, the first record of the file.
+    pub fn for_file(&self, delimiter: u8, header: &[String]) -> Result<Self, ColumnError> {
+        let mut indexes = vec![];
+        for column in &self.columns {
+            let index = match column {
+                Column::Index(i) => i - 1,
+                Column::Name(name) if self.has_header => match header.iter().position(|h| h.trim() == name) {
+                    Some(i) => i,
+                    None => return Err(ColumnError(name.clone())),
+                },
+                Column::Name(name) => return Err(ColumnError(name.clone())),
+            };
+            indexes.push(index);
+        }
+
+        let width = header.len().max(indexes.iter().map(|i| i + 1).max().unwrap_or(0));
+        let labels = (0..width)
+            .map(|i| match header.get(i) {
+                Some(name) if self.has_header && !name.is_empty() => name.clone(),
+                _ => (i + 1).to_string(),
+            })
+            .collect();
+
+        Ok(CsvColumnMatcher {
+            delimiter,
+            indexes: if self.columns.is_empty() { None } else { Some(indexes) },
+            labels,
+            ..self.clone()
+        })
+    }
+
+    /// Get the label of the column of `HmgMatch::field`.
+    pub fn column_label(&self, index: usize) -> Option<&str> {
+        self.labels.get(index).map(|l| l.as_str())
+    }
+
+    /// Find all matches in the columns of `line`, a record, as ranges of the line.
+    pub fn find_all(&self, line: &[u8]) -> Vec<HmgMatch> {
+        let mut result = vec![];
+
+        for (column, cell) in cells(line, self.delimiter).into_iter().enumerate() {
+            if self.indexes.as_ref().is_some_and(|indexes| !indexes.contains(&column)) {
+                continue;
+            }
+
+            for mut m in self.matcher.find_all(cell.text.as_bytes()) {
+                m.start = cell.offsets[m.start];
+                m.end = cell.offsets[m.end];
+                m.field = Some(column);
+                result.push(m);
+            }
+        }
+        result.sort_by_key(|m| (m.start, usize::MAX - m.end));
+
+        result
+    }
+}
+
+impl Matcher for CsvColumnMatcher {
+    type Captures = NoCaptures;
+    type Error = NoError;
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, NoError> {
+        // a record is a whole line, so split from the start of the line of `at`.
+        let result = find_in_records_by(haystack, at, RECORD_TERMINATOR, |line, min_start| {
+            let m = self.find_all(line).into_iter().find(|m| m.start >= min_start)?;
+            Some((m.start, m.end))
+        });
+
+        Ok(result.map(|(s, e)| Match::new(s, e)))
+    }
+
+    fn new_captures(&self) -> Result<NoCaptures, NoError> {
+        Ok(NoCaptures::new())
+    }
+}
+
+/// Decode the content of a cell of a record, such as a match of `CsvColumnMatcher`.
+pub fn decode_cell(raw: &[u8]) -> String {
+    String::from_utf8_lossy(raw).replace("\"\"", "\"")
+}
+
+/// A cell of a record, with a map back to the offsets of the line.
+struct Cell {
+    text: String,
+
+    /// Offset in the line of each byte of `text` (and `text.len()`).
+    offsets: Vec<usize>,
+}
+
+/// Split a record to the cells.
+fn cells(line: &[u8], delimiter: u8) -> Vec<Cell> {
+    let line = line.strip_suffix(&[RECORD_TERMINATOR]).unwrap_or(line);
+    let mut result = vec![];
+
+    let mut pos = 0;
+    loop {
+        let mut bytes = vec![];
+        let mut offsets = vec![];
+
+        if line.get(pos) == Some(&b'"') {
+            // a quoted cell. `""` is a quote.
+            pos += 1;
+            while pos < line.len() {
+                if line[pos] == b'"' && line.get(pos + 1) != Some(&b'"') {
+                    break;
+                }
+                offsets.push(pos);
+                bytes.push(line[pos]);
+                pos += if line[pos] == b'"' { 2 } else { 1 };
+            }
+            offsets.push(pos);
+            pos += 1;
+        } else {
+            while pos < line.len() && line[pos] != delimiter {
+                offsets.push(pos);
+                bytes.push(line[pos]);
+                pos += 1;
+            }
+            offsets.push(pos);
+        }
+
+        // a cell that is not valid UTF-8 (without `--encoding`) is not searched.
+        let text = String::from_utf8(bytes).unwrap_or_default();
+        result.push(Cell { text, offsets });
+
+        if pos >= line.len() {
+            break;
+        }
+        pos += 1;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hmglib::Homoglyphs;
+
+    use super::super::skeleton::SkeletonMatcher;
+
+    #[test]
+    fn tests_csv_column_matcher() {
+        let data = "id,name,note\r\n1,\"pаypal\nsupport\",paypal\r\n2,bob,\"say \"\"pаypal\"\"\"\r\n";
+        let Records { ranges, header } = records(data.as_bytes(), b',').unwrap();
+        assert_eq!(header, vec!["id", "name", "note"]);
+        assert_eq!(&data[ranges[1].0..ranges[1].1], "1,\"pаypal\nsupport\",paypal");
+
+        let mut text = vec![];
+        for (start, end) in ranges {
+            text.extend_from_slice(&data.as_bytes()[start..end]);
+            text.push(RECORD_TERMINATOR);
+        }
+
+        let homoglyphs = Homoglyphs::new();
+        let matcher = HmgMatcher::Skeleton(SkeletonMatcher::new(&homoglyphs, "paypal"));
+        let columns = vec![Column::parse("name"), Column::parse("3")];
+        let matcher = CsvColumnMatcher::new(matcher, columns, true, false)
+            .for_file(b',', &header)
+            .unwrap();
+
+        let lines: Vec<&[u8]> = text.split_inclusive(|b| *b == RECORD_TERMINATOR).collect();
+        let matches = matcher.find_all(lines[1]);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matcher.column_label(matches[0].field.unwrap()), Some("name"));
+
+        let matches = matcher.find_all(lines[2]);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(decode_cell(&lines[2][matches[0].start..matches[0].end]), "pаypal");
+
+        let columns = vec![Column::parse("mail")];
+        assert!(CsvColumnMatcher::new(HmgMatcher::Patterns(vec![]), columns, true, false)
+            .for_file(b',', &header)
+            .is_err());
+    }
+}
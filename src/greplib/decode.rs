@@ -0,0 +1,452 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+// Reference:
+//   - https://html.spec.whatwg.org/multipage/syntax.html#character-references
+//   - https://www.rfc-editor.org/rfc/rfc3986#section-2.1 (percent-encoding)
+//   - https://www.rfc-editor.org/rfc/rfc3492 (punycode)
+//   - https://www.rfc-editor.org/rfc/rfc2045#section-6.7 (quoted-printable)
+
+use grep_matcher::{Match, Matcher, NoCaptures, NoError};
+use memchr::{memchr, memrchr};
+
+use super::common::find_in_records;
+use super::matcher::{HmgMatch, HmgMatcher};
+
+/// An encoding that hides characters, decoded before matching.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decoding {
+    /// `&#x0441;`, `&#1089;` and `&amp;`.
+    Html,
+
+    /// `%D1%81`.
+    Url,
+
+    /// `xn--` labels of domain names.
+    Punycode,
+
+    /// `=D1=81`, and the soft line breaks (`=` at the end of a line), which join the lines.
+    QuotedPrintable,
+}
+
+impl Decoding {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "html" => Some(Decoding::Html),
+            "url" => Some(Decoding::Url),
+            "punycode" => Some(Decoding::Punycode),
+            "qp" => Some(Decoding::QuotedPrintable),
+            _ => None,
+        }
+    }
+}
+
+/// Decoded text, with a map back to the ranges of the original bytes.
+#[derive(Clone, Debug, Default)]
+pub struct Decoded {
+    pub bytes: Vec<u8>,
+
+    /// The range in the original bytes of each byte of `bytes`.
+    starts: Vec<usize>,
+    ends: Vec<usize>,
+}
+
+impl Decoded {
+    fn identity(text: &[u8]) -> Self {
+        Decoded {
+            bytes: text.to_vec(),
+            starts: (0..text.len()).collect(),
+            ends: (1..=text.len()).collect(),
+        }
+    }
+
+    /// Push `bytes` decoded from `start..end` of the input.
+    fn push(&mut self, bytes: &[u8], start: usize, end: usize) {
+        for b in bytes {
+            self.bytes.push(*b);
+            self.starts.push(start);
+            self.ends.push(end);
+        }
+    }
+
+    /// Map `next`, the decoded text of this text, back to the original bytes.
+    fn then(&self, next: Decoded) -> Decoded {
+        Decoded {
+            starts: next.starts.iter().map(|&s| self.starts[s]).collect(),
+            ends: next.ends.iter().map(|&e| self.ends[e - 1]).collect(),
+            bytes: next.bytes,
+        }
+    }
+
+    /// Convert a range of the decoded text to a range of the original bytes.
+    /// A range in the middle of an encoded sequence is widened to the whole sequence.
+    pub fn to_original(&self, start: usize, end: usize, original_len: usize) -> (usize, usize) {
+        let original_start = self.starts.get(start).cloned().unwrap_or(original_len);
+        if start == end {
+            return (original_start, original_start);
+        }
+
+        (original_start, self.ends[end - 1])
+    }
+}
+
+/// Decode `text` with each of `decodings` in order.
+pub fn decode(text: &[u8], decodings: &[Decoding]) -> Decoded {
+    let mut result = Decoded::identity(text);
+
+    for decoding in decodings {
+        let next = match decoding {
+            Decoding::Html => decode_html(&result.bytes),
+            Decoding::Url => decode_hex_escapes(&result.bytes, b'%'),
+            Decoding::QuotedPrintable => decode_hex_escapes(&result.bytes, b'='),
+            Decoding::Punycode => decode_punycode_labels(&result.bytes),
+        };
+        result = result.then(next);
+    }
+
+    result
+}
+
+/// Decode the character references. The `;` of a numeric reference is optional, like browsers.
+fn decode_html(text: &[u8]) -> Decoded {
+    let mut result = Decoded::default();
+
+    let mut i = 0;
+    while i < text.len() {
+        if text[i] == b'&' {
+            if let Some((c, len)) = parse_character_reference(&text[i..]) {
+                let mut buf = [0; 4];
+                result.push(c.encode_utf8(&mut buf).as_bytes(), i, i + len);
+                i += len;
+                continue;
+            }
+        }
+
+        result.push(&text[i..i + 1], i, i + 1);
+        i += 1;
+    }
+
+    result
+}
+
+/// Parse a character reference at the start of `text`, and get the character and the length.
+fn parse_character_reference(text: &[u8]) -> Option<(char, usize)> {
+    if let Some(rest) = text.strip_prefix(b"&#") {
+        let (radix, digits_start) = match rest.first() {
+            Some(b'x') | Some(b'X') => (16, 1),
+            _ => (10, 0),
+        };
+        let digits: Vec<u8> = rest[digits_start..]
+            .iter()
+            .take_while(|b| (**b as char).is_digit(radix))
+            .cloned()
+            .collect();
+        if digits.is_empty() || digits.len() > 8 {
+            return None;
+        }
+
+        let code = u32::from_str_radix(std::str::from_utf8(&digits).ok()?, radix).ok()?;
+        let mut len = 2 + digits_start + digits.len();
+        if text.get(len) == Some(&b';') {
+            len += 1;
+        }
+        return Some((char::from_u32(code).unwrap_or('\u{fffd}'), len));
+    }
+
+    const NAMED: [(&[u8], char); 6] = [
+        (b"&amp;", '&'),
+        (b"&lt;", '<'),
+        (b"&gt;", '>'),
+        (b"&quot;", '"'),
+        (b"&apos;", '\''),
+        (b"&nbsp;", '\u{a0}'),
+    ];
+    NAMED
+        .iter()
+        .find(|(name, _)| text.starts_with(name))
+        .map(|(name, c)| (*c, name.len()))
+}
+
+/// Decode `%XX` (`escape` is `%`) or `=XX` (`escape` is `=`).
+/// For quoted-printable, `=` at the end of a line is a soft line break, and is removed.
+fn decode_hex_escapes(text: &[u8], escape: u8) -> Decoded {
+    let mut result = Decoded::default();
+
+    let mut i = 0;
+    while i < text.len() {
+        if text[i] == escape {
+            let hex = text.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+            if let Some(b) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                result.push(&[b], i, i + 3);
+                i += 3;
+                continue;
+            }
+
+            // a soft line break is removed with the line break.
+            if escape == b'=' {
+                let rest = &text[i + 1..];
+                let soft_break = if rest.starts_with(b"\r\n") { 2 } else { 1 };
+                if rest.is_empty() || rest.starts_with(b"\n") || rest.starts_with(b"\r\n") {
+                    i += 1 + soft_break.min(rest.len());
+                    continue;
+                }
+            }
+        }
+
+        result.push(&text[i..i + 1], i, i + 1);
+        i += 1;
+    }
+
+    result
+}
+
+/// Decode the `xn--` labels. A label that is not valid punycode is kept.
+fn decode_punycode_labels(text: &[u8]) -> Decoded {
+    let is_label_byte = |b: u8| b.is_ascii_alphanumeric() || b == b'-';
+    let mut result = Decoded::default();
+
+    let mut i = 0;
+    while i < text.len() {
+        let is_label_start = i == 0 || !is_label_byte(text[i - 1]);
+        if is_label_start && text.len() >= i + 4 && text[i..i + 4].eq_ignore_ascii_case(b"xn--") {
+            let end = i + 4 + text[i + 4..].iter().take_while(|b| is_label_byte(**b)).count();
+            let label = String::from_utf8_lossy(&text[i + 4..end]).to_ascii_lowercase();
+
+            if let Some(decoded) = decode_punycode(&label) {
+                result.push(decoded.as_bytes(), i, end);
+                i = end;
+                continue;
+            }
+        }
+
+        result.push(&text[i..i + 1], i, i + 1);
+        i += 1;
+    }
+
+    result
+}
+
+/// Decode a punycode label without `xn--`.
+fn decode_punycode(input: &str) -> Option<String> {
+    const BASE: u32 = 36;
+    const T_MIN: u32 = 1;
+    const T_MAX: u32 = 26;
+    const SKEW: u32 = 38;
+    const DAMP: u32 = 700;
+
+    let adapt = |mut delta: u32, num_points: u32, is_first: bool| {
+        delta /= if is_first { DAMP } else { 2 };
+        delta += delta / num_points;
+        let mut k = 0;
+        while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+            delta /= BASE - T_MIN;
+            k += BASE;
+        }
+        k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+    };
+
+    let (basic, digits) = match input.rfind('-') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => ("", input),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    let mut output: Vec<char> = basic.chars().collect();
+
+    let (mut n, mut i, mut bias) = (128u32, 0u32, 72u32);
+    let mut digits = digits.bytes().peekable();
+    while digits.peek().is_some() {
+        let old_i = i;
+        let mut w = 1u32;
+        let mut k = BASE;
+        loop {
+            let digit = match digits.next()? {
+                b @ b'a'..=b'z' => (b - b'a') as u32,
+                b @ b'0'..=b'9' => (b - b'0') as u32 + 26,
+                _ => return None,
+            };
+            i = i.checked_add(digit.checked_mul(w)?)?;
+            let t = if k <= bias {
+                T_MIN
+            } else if k >= bias + T_MAX {
+                T_MAX
+            } else {
+                k - bias
+            };
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+
+        let num_points = output.len() as u32 + 1;
+        bias = adapt(i - old_i, num_points, old_i == 0);
+        n = n.checked_add(i / num_points)?;
+        i %= num_points;
+        output.insert(i as usize, char::from_u32(n)?);
+        i += 1;
+    }
+
+    Some(output.into_iter().collect())
+}
+
+/// Matcher that decodes each line with the decodings before matching.
+/// The matches are the ranges of the raw lines.
+#[derive(Clone, Debug)]
+pub struct DecodeMatcher {
+    matcher: Box<HmgMatcher>,
+    decodings: Vec<Decoding>,
+}
+
+impl DecodeMatcher {
+    pub fn new(matcher: HmgMatcher, decodings: Vec<Decoding>) -> Self {
+        DecodeMatcher {
+            matcher: Box::new(matcher),
+            decodings,
+        }
+    }
+
+    /// The matcher of the decoded lines.
+    pub fn inner(&self) -> &HmgMatcher {
+        &self.matcher
+    }
+
+    pub fn inner_mut(&mut self) -> &mut HmgMatcher {
+        &mut self.matcher
+    }
+
+    pub fn decodings(&self) -> &[Decoding] {
+        &self.decodings
+    }
+
+    /// Find all matches in the decoded `line`, as ranges of the raw line.
+    pub fn find_all(&self, line: &[u8]) -> Vec<HmgMatch> {
+        let decoded = decode(line, &self.decodings);
+
+        let mut result = vec![];
+        for mut m in self.matcher.find_all(&decoded.bytes) {
+            let (start, end) = decoded.to_original(m.start, m.end, line.len());
+            m.start = start;
+            m.end = end;
+            result.push(m);
+        }
+
+        result
+    }
+}
+
+impl Matcher for DecodeMatcher {
+    type Captures = NoCaptures;
+    type Error = NoError;
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, NoError> {
+        // an encoded sequence can be split by `at`, so decode from the start of the line.
+        let find = |line: &[u8], min_start| {
+            let m = self.find_all(line).into_iter().find(|m| m.start >= min_start)?;
+            Some((m.start, m.end))
+        };
+
+        let result = if self.decodings.contains(&Decoding::QuotedPrintable) {
+            find_in_soft_lines(haystack, at, find)
+        } else {
+            find_in_records(haystack, at, find)
+        };
+
+        Ok(result.map(|(s, e)| Match::new(s, e)))
+    }
+
+    fn new_captures(&self) -> Result<NoCaptures, NoError> {
+        Ok(NoCaptures::new())
+    }
+}
+
+/// Like `find_in_records`, but a line that ends with a soft line break of quoted-printable continues
+/// to the next line. The searcher gives the lines together only with `-U`.
+fn find_in_soft_lines<F>(haystack: &[u8], at: usize, find: F) -> Option<(usize, usize)>
+where
+    F: Fn(&[u8], usize) -> Option<(usize, usize)>,
+{
+    // whether the `\n` at `i` is the end of a soft line break.
+    let is_soft_break = |i: usize| {
+        let line = &haystack[..i];
+        line.strip_suffix(b"\r").unwrap_or(line).ends_with(b"=")
+    };
+
+    let mut line_start = at;
+    loop {
+        match memrchr(b'\n', &haystack[..line_start]) {
+            Some(i) if is_soft_break(i) => line_start = i,
+            Some(i) => {
+                line_start = i + 1;
+                break;
+            }
+            None => {
+                line_start = 0;
+                break;
+            }
+        }
+    }
+
+    while line_start < haystack.len() {
+        let mut pos = line_start;
+        let line_end = loop {
+            match memchr(b'\n', &haystack[pos..]) {
+                Some(i) if is_soft_break(pos + i) => pos += i + 1,
+                Some(i) => break pos + i + 1,
+                None => break haystack.len(),
+            }
+        };
+
+        let min_start = at.saturating_sub(line_start);
+        if let Some((s, e)) = find(&haystack[line_start..line_end], min_start) {
+            return Some((line_start + s, line_start + e));
+        }
+
+        line_start = line_end;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_decode() {
+        let all = [Decoding::Html, Decoding::Url, Decoding::Punycode, Decoding::QuotedPrintable];
+
+        let text = "p&#x0430;y%D1%80al =D0=B0 xn--80ak6aa92e.com".as_bytes();
+        let decoded = decode(text, &all);
+        assert_eq!(String::from_utf8_lossy(&decoded.bytes), "pаyрal а аррӏе.com");
+
+        // `а` is `&#x0430;`, and `р` is `%D1%80`.
+        assert_eq!(decoded.to_original(0, 4, text.len()), (0, 10));
+        assert_eq!(decoded.to_original(4, 6, text.len()), (10, 16));
+
+        // a part of a punycode label is the whole label.
+        let start = "pаyрal а ".len();
+        assert_eq!(decoded.to_original(start, start + 2, text.len()), (26, 40));
+
+        assert_eq!(decode_punycode("mnchen-3ya").as_deref(), Some("münchen"));
+        assert_eq!(String::from_utf8_lossy(&decode(b"soft=\nbreak", &all).bytes), "softbreak");
+        assert_eq!(String::from_utf8_lossy(&decode(b"100% &ampx", &all).bytes), "100% &ampx");
+    }
+
+    #[test]
+    fn tests_soft_line_break() {
+        use hmglib::Homoglyphs;
+
+        use super::super::skeleton::SkeletonMatcher;
+
+        let matcher = HmgMatcher::Skeleton(SkeletonMatcher::new(&Homoglyphs::new(), "paypal"));
+        let matcher = DecodeMatcher::new(matcher, vec![Decoding::QuotedPrintable]);
+
+        let text = b"hi\nlog in to p=D0=B0y=\r\npal\n";
+        let m = matcher.find_at(text, 0).unwrap().unwrap();
+        assert_eq!((m.start(), m.end()), (13, 27));
+    }
+}
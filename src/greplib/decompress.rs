@@ -0,0 +1,180 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use ruzstd::StreamingDecoder;
+
+/// The maximum size of a decompressed xz file without a limit, because it is decompressed in memory.
+const MAX_XZ_SIZE: u64 = 1 << 30;
+
+/// A compression format that is decompressed in-process with `-z/--search-zip`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Xz,
+    Bzip2,
+    Zstd,
+}
+
+impl Compression {
+    /// Get the format of `path` by its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "gz" => Some(Compression::Gzip),
+            "xz" => Some(Compression::Xz),
+            "bz2" => Some(Compression::Bzip2),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Wrap `rdr` with the decoder of this format.
+    /// Reading more than `limit` bytes of the decompressed data fails, against decompression bombs.
+    pub fn decoder<R: Read + 'static>(self, rdr: R, limit: Option<u64>) -> io::Result<Box<dyn Read>> {
+        let decoder: Box<dyn Read> = match self {
+            Compression::Gzip => Box::new(MultiGzDecoder::new(rdr)),
+            Compression::Bzip2 => Box::new(MultiBzDecoder::new(rdr)),
+            Compression::Zstd => match StreamingDecoder::new(rdr) {
+                Ok(decoder) => Box::new(decoder),
+                Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+            },
+
+            // lzma-rs has no streaming decoder of xz, so the whole file is decompressed,
+            // up to the limit or `MAX_XZ_SIZE`.
+            Compression::Xz => {
+                let mut buf = LimitedWriter {
+                    data: vec![],
+                    limit: limit.unwrap_or(MAX_XZ_SIZE),
+                };
+                lzma_rs::xz_decompress(&mut BufReader::new(rdr), &mut buf).map_err(|e| match e {
+                    lzma_rs::error::Error::IoError(e) => e,
+                    e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+                })?;
+                return Ok(Box::new(io::Cursor::new(buf.data)));
+            }
+        };
+
+        match limit {
+            Some(limit) => Ok(Box::new(LimitedReader {
+                rdr: decoder,
+                remaining: limit,
+                limit,
+            })),
+            None => Ok(decoder),
+        }
+    }
+}
+
+/// Open `path` as a decompressed stream, if it is a compressed file.
+/// `limit` is the maximum size of the decompressed data, such as `--max-filesize`.
+pub fn open(path: &Path, limit: Option<u64>) -> io::Result<Option<Box<dyn Read>>> {
+    match Compression::from_path(path) {
+        Some(compression) => Ok(Some(compression.decoder(File::open(path)?, limit)?)),
+        None => Ok(None),
+    }
+}
+
+/// The error of decompressed data over the limit.
+#[derive(Debug)]
+struct LimitError(u64);
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "decompressed data exceeds the limit of {} bytes", self.0)
+    }
+}
+
+impl Error for LimitError {}
+
+fn limit_error(limit: u64) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, LimitError(limit))
+}
+
+/// Whether `e` is because the decompressed data is over the limit given to `Compression::decoder`.
+pub fn is_limit_error(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|e| e.is::<LimitError>())
+}
+
+/// A reader that fails after `limit` bytes.
+struct LimitedReader<R> {
+    rdr: R,
+    remaining: u64,
+    limit: u64,
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.rdr.read(buf)?;
+        if n as u64 > self.remaining {
+            return Err(limit_error(self.limit));
+        }
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// A buffer that fails to grow over `limit` bytes.
+struct LimitedWriter {
+    data: Vec<u8>,
+    limit: u64,
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if (self.data.len() + buf.len()) as u64 > self.limit {
+            return Err(limit_error(self.limit));
+        }
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+
+    #[test]
+    fn tests_gzip_decoder() {
+        assert_eq!(Compression::from_path(Path::new("a.log.GZ")), Some(Compression::Gzip));
+        assert_eq!(Compression::from_path(Path::new("a.zst")), Some(Compression::Zstd));
+        assert_eq!(Compression::from_path(Path::new("a.log")), None);
+
+        let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all("ㄘんこ\n".as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut text = String::new();
+        Compression::Gzip
+            .decoder(io::Cursor::new(compressed.clone()), None)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "ㄘんこ\n");
+
+        // the decompressed size is limited, not the compressed size.
+        let mut text = String::new();
+        let result = Compression::Gzip
+            .decoder(io::Cursor::new(compressed), Some(4))
+            .unwrap()
+            .read_to_string(&mut text);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,656 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+// Reference:
+//   - https://www.ecma-international.org/publications-and-standards/standards/ecma-376/ (OOXML)
+//   - https://docs.oasis-open.org/office/OpenDocument/v1.3/ (ODF)
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read, Seek};
+use std::path::Path;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+/// A format of office document whose text is extracted before searching.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DocumentKind {
+    Docx,
+    Xlsx,
+    Pptx,
+
+    /// OpenDocument. The text of all of them is in `content.xml`.
+    Odt,
+    Ods,
+    Odp,
+}
+
+impl DocumentKind {
+    /// Get the format of `path` by its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "docx" | "docm" => Some(DocumentKind::Docx),
+            "xlsx" | "xlsm" => Some(DocumentKind::Xlsx),
+            "pptx" | "pptm" => Some(DocumentKind::Pptx),
+            "odt" => Some(DocumentKind::Odt),
+            "ods" => Some(DocumentKind::Ods),
+            "odp" => Some(DocumentKind::Odp),
+            _ => None,
+        }
+    }
+}
+
+/// The extracted text of a part of a document, such as a slide or a cell of a sheet.
+/// Each paragraph is a line, so the line number of a match is the paragraph number.
+/// A cell is a single line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Part {
+    /// The name of the slide, or the sheet and the address of the cell like `Sheet1!C3`.
+    /// None if the document is a single part.
+    pub name: Option<String>,
+    pub text: String,
+}
+
+/// Extract the text of the document in `rdr`.
+/// A part of the document (such as `word/document.xml` or a sheet) larger than `limit` bytes
+/// after decompression is an error, so a zip bomb is not read into memory.
+pub fn extract<R: Read + Seek>(kind: DocumentKind, rdr: R, limit: Option<u64>) -> io::Result<Vec<Part>> {
+    let mut archive = ZipArchive::new(rdr).map_err(zip_to_io_error)?;
+
+    match kind {
+        DocumentKind::Docx => {
+            let xml = read_entry(&mut archive, "word/document.xml", limit)?;
+            Ok(vec![single_part(&paragraphs(&xml, &PARAGRAPH_RULES)?)])
+        }
+        DocumentKind::Odt => {
+            let xml = read_entry(&mut archive, "content.xml", limit)?;
+            Ok(vec![single_part(&paragraphs(&xml, &ODF_RULES)?)])
+        }
+        DocumentKind::Ods => {
+            let xml = read_entry(&mut archive, "content.xml", limit)?;
+            let cells = table_cells(&xml)?;
+            Ok(cells
+                .into_iter()
+                .map(|(name, text)| Part {
+                    name: Some(name),
+                    text: text + "\n",
+                })
+                .collect())
+        }
+        DocumentKind::Odp => {
+            let xml = read_entry(&mut archive, "content.xml", limit)?;
+            let pages = draw_pages(&xml)?;
+            Ok(pages
+                .iter()
+                .enumerate()
+                .map(|(i, lines)| Part {
+                    name: Some(format!("slide{}", i + 1)),
+                    ..single_part(lines)
+                })
+                .collect())
+        }
+        DocumentKind::Pptx => extract_slides(&mut archive, limit),
+        DocumentKind::Xlsx => extract_sheets(&mut archive, limit),
+    }
+}
+
+/// How to find the paragraphs and the text in an XML.
+struct TextRules {
+    /// The local names of the paragraph elements.
+    paragraph: &'static [&'static [u8]],
+
+    /// The local name of the text elements. If None, all text in a paragraph is used.
+    text: Option<&'static [u8]>,
+
+    /// The local names of the elements whose text is not used, such as the ruby of the shared strings.
+    skip: &'static [&'static [u8]],
+}
+
+/// The rules of WordprocessingML (`w:p`, `w:t`) and DrawingML (`a:p`, `a:t`).
+const PARAGRAPH_RULES: TextRules = TextRules {
+    paragraph: &[b"p"],
+    text: Some(b"t"),
+    skip: &[],
+};
+
+/// The rules of ODF (`text:p`, `text:h`).
+const ODF_RULES: TextRules = TextRules {
+    paragraph: &[b"p", b"h"],
+    text: None,
+    skip: &[b"annotation"],
+};
+
+/// The rules of the shared strings of SpreadsheetML (`si`, `t`). `rPh` is the phonetic reading.
+const SHARED_STRING_RULES: TextRules = TextRules {
+    paragraph: &[b"si"],
+    text: Some(b"t"),
+    skip: &[b"rPh"],
+};
+
+/// Get the text of each paragraph of `xml`.
+fn paragraphs(xml: &[u8], rules: &TextRules) -> io::Result<Vec<String>> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = vec![];
+
+    let mut result = vec![];
+    let mut line = String::new();
+    let (mut paragraph_depth, mut text_depth, mut skip_depth) = (0, 0, 0);
+
+    loop {
+        match reader.read_event_into(&mut buf).map_err(xml_to_io_error)? {
+            Event::Start(e) => {
+                let name = e.local_name();
+                if rules.paragraph.contains(&name.as_ref()) {
+                    paragraph_depth += 1;
+                } else if rules.text == Some(name.as_ref()) {
+                    text_depth += 1;
+                } else if rules.skip.contains(&name.as_ref()) {
+                    skip_depth += 1;
+                }
+            }
+            Event::Empty(e) if paragraph_depth > 0 && skip_depth == 0 => push_special(&mut line, &e),
+            Event::Text(t) if paragraph_depth > 0 && skip_depth == 0 && (rules.text.is_none() || text_depth > 0) => {
+                line.push_str(&t.unescape().map_err(xml_to_io_error)?);
+            }
+            Event::End(e) => {
+                let name = e.local_name();
+                if rules.paragraph.contains(&name.as_ref()) {
+                    paragraph_depth -= 1;
+                    if paragraph_depth == 0 {
+                        result.push(std::mem::take(&mut line));
+                    }
+                } else if rules.text == Some(name.as_ref()) {
+                    text_depth -= 1;
+                } else if rules.skip.contains(&name.as_ref()) {
+                    skip_depth -= 1;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(result)
+}
+
+/// Write the text of an empty element in a paragraph, such as a tab.
+/// A line break in a paragraph is a space, to keep a paragraph in a line.
+fn push_special(line: &mut String, e: &BytesStart) {
+    match e.local_name().as_ref() {
+        b"tab" => line.push('\t'),
+        b"br" | b"cr" | b"line-break" => line.push(' '),
+
+        // `text:s` is `text:c` spaces.
+        b"s" => {
+            let count = attribute(e, b"c").and_then(|c| c.parse().ok()).unwrap_or(1);
+            line.push_str(&" ".repeat(count));
+        }
+        _ => {}
+    }
+}
+
+/// Get the value of the attribute of the local name `key`.
+fn attribute(e: &BytesStart, key: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == key)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+fn single_part(lines: &[String]) -> Part {
+    Part {
+        name: None,
+        text: lines.join("\n") + "\n",
+    }
+}
+
+/// Extract the slides of a pptx, in the order of their numbers.
+fn extract_slides<R: Read + Seek>(archive: &mut ZipArchive<R>, limit: Option<u64>) -> io::Result<Vec<Part>> {
+    let mut slides: Vec<(u32, String)> = archive
+        .file_names()
+        .filter_map(|name| {
+            let number = name.strip_prefix("ppt/slides/slide")?.strip_suffix(".xml")?;
+            Some((number.parse().ok()?, name.to_string()))
+        })
+        .collect();
+    slides.sort();
+
+    let mut result = vec![];
+    for (number, entry) in slides {
+        let xml = read_entry(archive, &entry, limit)?;
+        let part = single_part(&paragraphs(&xml, &PARAGRAPH_RULES)?);
+        result.push(Part {
+            name: Some(format!("slide{}", number)),
+            ..part
+        });
+    }
+
+    Ok(result)
+}
+
+/// Extract the cells of the sheets of a xlsx, in the order of the workbook.
+fn extract_sheets<R: Read + Seek>(archive: &mut ZipArchive<R>, limit: Option<u64>) -> io::Result<Vec<Part>> {
+    let shared_strings = match read_entry(archive, "xl/sharedStrings.xml", limit) {
+        Ok(xml) => paragraphs(&xml, &SHARED_STRING_RULES)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(e),
+    };
+
+    // the path of each sheet is in the relationships of the workbook.
+    let mut targets = HashMap::new();
+    for e in elements(&read_entry(archive, "xl/_rels/workbook.xml.rels", limit)?, b"Relationship")? {
+        if let (Some(id), Some(target)) = (attribute(&e, b"Id"), attribute(&e, b"Target")) {
+            let target = match target.strip_prefix('/') {
+                Some(absolute) => absolute.to_string(),
+                None => format!("xl/{}", target),
+            };
+            targets.insert(id, target);
+        }
+    }
+
+    let mut result = vec![];
+    for e in elements(&read_entry(archive, "xl/workbook.xml", limit)?, b"sheet")? {
+        let entry = match attribute(&e, b"id").and_then(|id| targets.get(&id)) {
+            Some(entry) => entry.clone(),
+            None => continue,
+        };
+        let xml = read_entry(archive, &entry, limit)?;
+
+        let sheet = attribute(&e, b"name").unwrap_or_default();
+        for (address, text) in sheet_cells(&xml, &shared_strings)? {
+            result.push(Part {
+                name: Some(format!("{}!{}", sheet, address)),
+                text: text + "\n",
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+/// Get the elements of the local name `name` in `xml`.
+fn elements(xml: &[u8], name: &[u8]) -> io::Result<Vec<BytesStart<'static>>> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = vec![];
+    let mut result = vec![];
+
+    loop {
+        match reader.read_event_into(&mut buf).map_err(xml_to_io_error)? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == name => result.push(e.into_owned()),
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(result)
+}
+
+/// Get the address (like `C3`) and the text of each cell of a sheet that is not empty, in order of rows.
+fn sheet_cells(xml: &[u8], shared_strings: &[String]) -> io::Result<Vec<(String, String)>> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = vec![];
+
+    let mut cells: BTreeMap<(u32, u32), String> = BTreeMap::new();
+    let (mut row, mut column) = (0, 0);
+    let mut cell_type = None;
+    let mut value = String::new();
+    let (mut value_depth, mut skip_depth) = (0, 0);
+
+    loop {
+        match reader.read_event_into(&mut buf).map_err(xml_to_io_error)? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"row" => {
+                    row = attribute(&e, b"r").and_then(|r| r.parse().ok()).unwrap_or(row + 1);
+                    column = 0;
+                }
+                b"c" => {
+                    let reference = attribute(&e, b"r").and_then(|r| parse_cell_reference(&r));
+                    match reference {
+                        Some((c, r)) => {
+                            column = c;
+                            row = r;
+                        }
+                        None => column += 1,
+                    }
+                    cell_type = attribute(&e, b"t");
+                    value.clear();
+                }
+                b"v" | b"t" => value_depth += 1,
+                b"rPh" => skip_depth += 1,
+                _ => {}
+            },
+            Event::Text(t) if value_depth > 0 && skip_depth == 0 => {
+                value.push_str(&t.unescape().map_err(xml_to_io_error)?);
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"c" => {
+                    let text = match cell_type.as_deref() {
+                        Some("s") => value
+                            .trim()
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|i| shared_strings.get(i))
+                            .cloned()
+                            .unwrap_or_default(),
+                        _ => value.clone(),
+                    };
+                    if !text.is_empty() {
+                        // a cell is a line, so the line breaks in a cell are spaces.
+                        let text = text.replace(['\r', '\n'], " ");
+                        cells.insert((row, column), text);
+                    }
+                }
+                b"v" | b"t" => value_depth -= 1,
+                b"rPh" => skip_depth -= 1,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(cells
+        .into_iter()
+        .map(|((row, column), text)| (cell_reference(column, row), text))
+        .collect())
+}
+
+/// Get the name (like `Sheet1!C3`) and the text of each cell of the tables of an ODF spreadsheet
+/// that is not empty, in order of the sheets and the rows. The paragraphs of a cell are joined by spaces.
+fn table_cells(xml: &[u8]) -> io::Result<Vec<(String, String)>> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = vec![];
+
+    let mut result = vec![];
+    let mut sheet = String::new();
+    let (mut row, mut column) = (0, 0);
+    let (mut row_repeat, mut column_repeat) = (1, 1);
+    let mut cell: Option<String> = None;
+    let (mut paragraph_depth, mut skip_depth) = (0, 0);
+
+    // a run of repeated rows or cells is a single element, such as the empty rows up to the end of the sheet.
+    let repeat = |e: &BytesStart, key: &[u8]| attribute(e, key).and_then(|n| n.parse().ok()).unwrap_or(1);
+
+    loop {
+        match reader.read_event_into(&mut buf).map_err(xml_to_io_error)? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"table" => {
+                    sheet = attribute(&e, b"name").unwrap_or_default();
+                    row = 0;
+                }
+                b"table-row" => {
+                    row += 1;
+                    row_repeat = repeat(&e, b"number-rows-repeated");
+                    column = 0;
+                }
+                b"table-cell" | b"covered-table-cell" => {
+                    column += 1;
+                    column_repeat = repeat(&e, b"number-columns-repeated");
+                    cell = Some(String::new());
+                }
+                b"p" | b"h" if skip_depth == 0 => {
+                    if let Some(text) = cell.as_mut().filter(|t| paragraph_depth == 0 && !t.is_empty()) {
+                        text.push(' ');
+                    }
+                    paragraph_depth += 1;
+                }
+                b"annotation" => skip_depth += 1,
+                _ => {}
+            },
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"table-row" => row += repeat(&e, b"number-rows-repeated"),
+                b"table-cell" | b"covered-table-cell" => column += repeat(&e, b"number-columns-repeated"),
+                _ if paragraph_depth > 0 && skip_depth == 0 => {
+                    if let Some(text) = cell.as_mut() {
+                        push_special(text, &e);
+                    }
+                }
+                _ => {}
+            },
+            Event::Text(t) if paragraph_depth > 0 && skip_depth == 0 => {
+                if let Some(text) = cell.as_mut() {
+                    text.push_str(&t.unescape().map_err(xml_to_io_error)?);
+                }
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"table-row" => row += row_repeat - 1,
+                b"table-cell" | b"covered-table-cell" => {
+                    // a repeated cell is named by the first one.
+                    if let Some(text) = cell.take().filter(|t| !t.is_empty()) {
+                        result.push((format!("{}!{}", sheet, cell_reference(column, row)), text));
+                    }
+                    column += column_repeat - 1;
+                }
+                b"p" | b"h" if skip_depth == 0 => paragraph_depth -= 1,
+                b"annotation" => skip_depth -= 1,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(result)
+}
+
+/// Get the paragraphs of each page (slide) of an ODF presentation. The speaker notes are skipped, like a pptx.
+fn draw_pages(xml: &[u8]) -> io::Result<Vec<Vec<String>>> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = vec![];
+
+    let mut result = vec![];
+    let mut lines = vec![];
+    let mut line = String::new();
+    let (mut page_depth, mut paragraph_depth, mut skip_depth) = (0, 0, 0);
+
+    loop {
+        match reader.read_event_into(&mut buf).map_err(xml_to_io_error)? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"page" => page_depth += 1,
+                b"p" | b"h" if page_depth > 0 && skip_depth == 0 => paragraph_depth += 1,
+                b"notes" | b"annotation" => skip_depth += 1,
+                _ => {}
+            },
+            Event::Empty(e) if e.local_name().as_ref() == b"page" => result.push(vec![]),
+            Event::Empty(e) if paragraph_depth > 0 && skip_depth == 0 => push_special(&mut line, &e),
+            Event::Text(t) if paragraph_depth > 0 && skip_depth == 0 => {
+                line.push_str(&t.unescape().map_err(xml_to_io_error)?);
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"page" => {
+                    page_depth -= 1;
+                    result.push(std::mem::take(&mut lines));
+                }
+                b"p" | b"h" if paragraph_depth > 0 && skip_depth == 0 => {
+                    paragraph_depth -= 1;
+                    if paragraph_depth == 0 {
+                        lines.push(std::mem::take(&mut line));
+                    }
+                }
+                b"notes" | b"annotation" => skip_depth -= 1,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(result)
+}
+
+/// Format the column and the row, both 1-based, as a cell reference like `B12`.
+fn cell_reference(column: u32, row: u32) -> String {
+    let mut letters = vec![];
+    let mut column = column;
+    while column > 0 {
+        letters.push(b'A' + ((column - 1) % 26) as u8);
+        column = (column - 1) / 26;
+    }
+    letters.reverse();
+
+    format!("{}{}", String::from_utf8_lossy(&letters), row)
+}
+
+/// Parse a cell reference like `B12` to the column and the row, both 1-based.
+fn parse_cell_reference(reference: &str) -> Option<(u32, u32)> {
+    let split = reference.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = reference.split_at(split);
+    if letters.is_empty() {
+        return None;
+    }
+
+    let mut column = 0u32;
+    for c in letters.chars() {
+        if !c.is_ascii_uppercase() {
+            return None;
+        }
+        column = column.checked_mul(26)?.checked_add(c as u32 - 'A' as u32 + 1)?;
+    }
+
+    Some((column, digits.parse().ok()?))
+}
+
+/// Read the entry `name`, failing over `limit` bytes. The size in the header is not trusted.
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str, limit: Option<u64>) -> io::Result<Vec<u8>> {
+    let mut file = archive.by_name(name).map_err(zip_to_io_error)?;
+    let mut data = vec![];
+
+    match limit {
+        Some(limit) => {
+            file.by_ref().take(limit + 1).read_to_end(&mut data)?;
+            if data.len() as u64 > limit {
+                let message = format!("document part {} exceeds the limit of {} bytes", name, limit);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+        }
+        None => {
+            file.read_to_end(&mut data)?;
+        }
+    }
+
+    Ok(data)
+}
+
+fn zip_to_io_error(e: ZipError) -> io::Error {
+    match e {
+        ZipError::Io(e) => e,
+        ZipError::FileNotFound => io::Error::new(io::ErrorKind::NotFound, "invalid document: missing part"),
+        e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+    }
+}
+
+fn xml_to_io_error(e: quick_xml::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Cursor, Write};
+
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    fn build_zip(files: &[(&str, &str)]) -> Cursor<Vec<u8>> {
+        let mut writer = ZipWriter::new(Cursor::new(vec![]));
+        for (name, data) in files {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+        }
+        Cursor::new(writer.finish().unwrap().into_inner())
+    }
+
+    #[test]
+    fn tests_extract_limit() {
+        let xml = format!("<w:document><w:body><w:p><w:t>{}</w:t></w:p></w:body></w:document>", "x".repeat(1024));
+        let docx = || build_zip(&[("word/document.xml", &xml)]);
+
+        let parts = extract(DocumentKind::Docx, docx(), Some(2048)).unwrap();
+        assert_eq!(parts[0].text.len(), 1025);
+
+        let e = extract(DocumentKind::Docx, docx(), Some(512)).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn tests_table_cells() {
+        let xml = r#"<office:document-content><office:body><office:spreadsheet>
+            <table:table table:name="Sheet1">
+                <table:table-row><table:table-cell><text:p>ログイン</text:p></table:table-cell></table:table-row>
+                <table:table-row table:number-rows-repeated="2"><table:table-cell/></table:table-row>
+                <table:table-row>
+                    <table:table-cell table:number-columns-repeated="2"/>
+                    <table:table-cell><text:p>p<text:span>а</text:span>y</text:p><text:p>pal</text:p></table:table-cell>
+                    <table:table-cell><office:annotation><text:p>note</text:p></office:annotation></table:table-cell>
+                </table:table-row>
+            </table:table>
+            <table:table table:name="Sheet2">
+                <table:table-row><table:table-cell/><table:table-cell><text:p>42</text:p></table:table-cell></table:table-row>
+            </table:table>
+        </office:spreadsheet></office:body></office:document-content>"#;
+
+        assert_eq!(
+            table_cells(xml.as_bytes()).unwrap(),
+            vec![
+                ("Sheet1!A1".to_string(), "ログイン".to_string()),
+                ("Sheet1!C4".to_string(), "pаy pal".to_string()),
+                ("Sheet2!B1".to_string(), "42".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn tests_draw_pages() {
+        let xml = r#"<office:document-content><office:body><office:presentation>
+            <draw:page draw:name="page1"><draw:frame><draw:text-box><text:p>login</text:p><text:p>pаypal</text:p></draw:text-box></draw:frame>
+                <presentation:notes><draw:frame><draw:text-box><text:p>note</text:p></draw:text-box></draw:frame></presentation:notes>
+            </draw:page>
+            <draw:page draw:name="page2"/>
+            <draw:page draw:name="page3"><draw:frame><draw:text-box><text:p>end</text:p></draw:text-box></draw:frame></draw:page>
+        </office:presentation></office:body></office:document-content>"#;
+
+        assert_eq!(
+            draw_pages(xml.as_bytes()).unwrap(),
+            vec![vec!["login".to_string(), "pаypal".to_string()], vec![], vec!["end".to_string()]]
+        );
+    }
+
+    #[test]
+    fn tests_sheet_cells() {
+        assert_eq!(parse_cell_reference("B12"), Some((2, 12)));
+        assert_eq!(parse_cell_reference("AA1"), Some((27, 1)));
+        assert_eq!(cell_reference(27, 1), "AA1");
+        assert_eq!(cell_reference(26, 3), "Z3");
+
+        let shared_strings = paragraphs(
+            "<sst><si><t>ログイン</t></si><si><r><t>pаy</t></r><r><t>pal</t></r><rPh><t>ペイパル</t></rPh></si></sst>".as_bytes(),
+            &SHARED_STRING_RULES,
+        )
+        .unwrap();
+        assert_eq!(shared_strings, vec!["ログイン", "pаypal"]);
+
+        let xml = r#"<worksheet><sheetData>
+            <row r="1"><c r="A1" t="s"><v>0</v></c></row>
+            <row r="3"><c r="C3" t="s"><v>1</v></c><c r="D3"><v>42</v></c></row>
+        </sheetData></worksheet>"#;
+        assert_eq!(
+            sheet_cells(xml.as_bytes(), &shared_strings).unwrap(),
+            vec![
+                ("A1".to_string(), "ログイン".to_string()),
+                ("C3".to_string(), "pаypal".to_string()),
+                ("D3".to_string(), "42".to_string()),
+            ]
+        );
+    }
+}
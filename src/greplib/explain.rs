@@ -0,0 +1,222 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+use hmglib::Homoglyphs;
+
+use super::common::char_indices_lossy;
+
+/// A character of a match that is not in the pattern.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Substitution {
+    pub start: usize,
+    pub end: usize,
+    pub ch: char,
+
+    /// The pattern character that `ch` impersonates, if any.
+    pub original: Option<char>,
+}
+
+impl Substitution {
+    /// Format like `ㄘ U+3118 BOPOMOFO LETTER C => ち U+3061 HIRAGANA LETTER TI`.
+    pub fn describe(&self) -> String {
+        let mut text = describe_char(self.ch);
+        if let Some(original) = self.original {
+            text.push_str(" => ");
+            text.push_str(&describe_char(original));
+        }
+        text
+    }
+}
+
+/// A character of a match, with the pattern character it stands for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CharMapping {
+    pub start: usize,
+    pub end: usize,
+    pub ch: char,
+
+    /// The pattern character that `ch` stands for, if any.
+    pub pattern_char: Option<char>,
+
+    /// Whether `ch` differs from the pattern character.
+    pub substituted: bool,
+}
+
+/// Finds the characters of a match that differ from the pattern characters,
+/// that is, the spoofed characters.
+/// The case, width and kana options of `Homoglyphs` are respected, so a full-width
+/// character is not a substitution with `--cjk-width`.
+#[derive(Clone, Debug)]
+pub struct Explainer {
+    homoglyphs: Homoglyphs,
+    patterns: Vec<String>,
+
+    /// The characters of each pattern.
+    pattern_chars: Vec<Vec<PatternChar>>,
+}
+
+/// A character of a pattern, with the forms that a matched character is compared in.
+#[derive(Clone, Debug)]
+struct PatternChar {
+    ch: char,
+    normalized: String,
+    skeleton: String,
+}
+
+impl Explainer {
+    pub fn new(homoglyphs: &Homoglyphs, patterns: &[String]) -> Self {
+        let pattern_chars = patterns
+            .iter()
+            .map(|p| {
+                p.chars()
+                    .map(|c| PatternChar {
+                        ch: c,
+                        normalized: homoglyphs.get_normalized_char(c),
+                        skeleton: homoglyphs.get_skeleton_char(c),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Explainer {
+            homoglyphs: homoglyphs.clone(),
+            patterns: patterns.to_vec(),
+            pattern_chars,
+        }
+    }
+
+    /// The patterns given to `new`.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Map each character of `text`, the bytes of a match, to the pattern character at the same position.
+    /// With several patterns, the pattern with the fewest substitutions is used.
+    /// The offsets are relative to `text`.
+    pub fn mapping(&self, text: &[u8]) -> Vec<CharMapping> {
+        let chars = char_indices_lossy(text);
+
+        match self.pattern_chars.iter().map(|p| self.align(&chars, p)).min_by_key(|m| {
+            m.iter().filter(|c| c.substituted).count()
+        }) {
+            Some(mapping) => mapping,
+            None => self.align(&chars, &[]),
+        }
+    }
+
+    /// Map each character of `chars` to the character of `pattern` at the same position.
+    /// A repeated character (`--elongation`) maps to the previous pattern character, and the
+    /// characters of a homoglyph longer than one character (such as `rn` for `m`) map to the same one.
+    fn align(&self, chars: &[(usize, usize, char)], pattern: &[PatternChar]) -> Vec<CharMapping> {
+        let mut result = vec![];
+
+        let mut pos = 0;
+        for (i, &(start, end, c)) in chars.iter().enumerate() {
+            // the line break of a word wrapped by `-U` is not a substitution.
+            let is_line_break = matches!(c, '\r' | '\n' | ' ' | '\t')
+                || (c == '-' && chars.get(i + 1).is_some_and(|n| n.2 == '\r' || n.2 == '\n'));
+
+            let normalized = self.homoglyphs.get_normalized_char(c);
+            let skeleton = self.homoglyphs.get_skeleton_char(c);
+            let current = pattern.get(pos);
+            let previous = pos.checked_sub(1).and_then(|p| pattern.get(p));
+            let following = pattern.get(pos + 1);
+
+            let (pattern_char, substituted) = if current.is_some_and(|p| p.normalized == normalized) {
+                pos += 1;
+                (current, false)
+            } else if is_line_break {
+                (None, false)
+            } else if previous.is_some_and(|p| p.normalized == normalized) {
+                (previous, false)
+            } else if current.is_some_and(|p| p.skeleton == skeleton) {
+                pos += 1;
+                (current, true)
+            } else if previous.is_some_and(|p| p.skeleton == skeleton) {
+                (previous, true)
+            } else if following.is_some_and(|p| p.normalized == normalized) {
+                // the current pattern character is missing, as in approximate matching.
+                pos += 2;
+                (following, false)
+            } else {
+                // a part of a longer homoglyph, or a wrong character of approximate matching.
+                // the next pattern character starts where the rest of the match fits it again.
+                let next = chars.get(i + 1).map(|n| self.homoglyphs.get_normalized_char(n.2));
+                if next.is_none() || next == following.map(|p| p.normalized.clone()) {
+                    pos += 1;
+                }
+                (current, true)
+            };
+
+            result.push(CharMapping {
+                start,
+                end,
+                ch: c,
+                pattern_char: pattern_char.map(|p| p.ch),
+                substituted,
+            });
+        }
+
+        result
+    }
+
+    /// Find the substitutions in `text`, the bytes of a match.
+    /// The offsets are relative to `text`.
+    pub fn substitutions(&self, text: &[u8]) -> Vec<Substitution> {
+        self.mapping(text)
+            .into_iter()
+            .filter(|m| m.substituted)
+            .map(|m| Substitution {
+                start: m.start,
+                end: m.end,
+                ch: m.ch,
+                original: m.pattern_char,
+            })
+            .collect()
+    }
+}
+
+/// Format like `ち U+3061 HIRAGANA LETTER TI`.
+fn describe_char(c: char) -> String {
+    let name = match unicode_names2::name(c) {
+        Some(name) => name.to_string(),
+        None => "<unnamed>".to_string(),
+    };
+
+    format!("{} U+{:04X} {}", c, c as u32, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_substitutions() {
+        let explainer = Explainer::new(&Homoglyphs::new(), &["ちんこ".to_string()]);
+
+        let subs = explainer.substitutions("ㄘんこ".as_bytes());
+        assert_eq!(subs.len(), 1);
+        assert_eq!(subs[0].start, 0);
+        assert_eq!(subs[0].original, Some('ち'));
+        assert_eq!(
+            subs[0].describe(),
+            "ㄘ U+3118 BOPOMOFO LETTER C => ち U+3061 HIRAGANA LETTER TI"
+        );
+
+        assert!(explainer.substitutions("ちんこ".as_bytes()).is_empty());
+    }
+
+    #[test]
+    fn tests_substitutions_by_position() {
+        let explainer = Explainer::new(&Homoglyphs::new(), &["microsoft".to_string()]);
+
+        // `r` and `n` are in the pattern, but not at these positions.
+        let subs = explainer.substitutions("rnicrosoft".as_bytes());
+        assert_eq!(subs.iter().map(|s| (s.ch, s.original)).collect::<Vec<_>>(), vec![('r', Some('m')), ('n', Some('m'))]);
+
+        // `o` is in the pattern, but `c` is at this position.
+        let subs = explainer.substitutions("miorosoft".as_bytes());
+        assert_eq!(subs.iter().map(|s| (s.ch, s.original)).collect::<Vec<_>>(), vec![('o', Some('c'))]);
+    }
+}
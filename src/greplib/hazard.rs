@@ -0,0 +1,353 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+// Reference:
+//   - https://trojansource.codes/ (CVE-2021-42574)
+//   - https://www.unicode.org/reports/tr39/#Restriction_Level_Detection
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use grep_matcher::{Match, Matcher, NoCaptures, NoError};
+use grep_searcher::{Searcher, Sink, SinkMatch};
+use unicode_script::{Script, UnicodeScript};
+
+use super::common::char_indices_lossy;
+use super::{Config, ScanStatus};
+
+/// Script combinations that are not reported as mixed script.
+/// Same as the `Highly Restrictive` level of UTS #39.
+const ALLOWED_SCRIPT_SETS: [&[Script]; 3] = [
+    &[Script::Latin, Script::Han, Script::Hiragana, Script::Katakana],
+    &[Script::Latin, Script::Han, Script::Bopomofo],
+    &[Script::Latin, Script::Han, Script::Hangul],
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HazardKind {
+    /// Bidirectional control and override characters.
+    Bidi,
+
+    /// Zero-width and other invisible characters.
+    Invisible,
+
+    /// Tag characters (U+E0000..U+E007F).
+    Tag,
+
+    /// A character whose script differs from the rest of the word.
+    MixedScript,
+}
+
+impl HazardKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HazardKind::Bidi => "bidi-control",
+            HazardKind::Invisible => "invisible",
+            HazardKind::Tag => "tag",
+            HazardKind::MixedScript => "mixed-script",
+        }
+    }
+}
+
+/// A risky character found in a line.
+#[derive(Clone, Debug)]
+pub struct Hazard {
+    pub start: usize,
+    pub end: usize,
+    pub ch: char,
+    pub kind: HazardKind,
+}
+
+impl Hazard {
+    /// Get the code point name of the character, e.g. `RIGHT-TO-LEFT OVERRIDE`.
+    pub fn name(&self) -> String {
+        match unicode_names2::name(self.ch) {
+            Some(name) => name.to_string(),
+            None => "<unnamed>".to_string(),
+        }
+    }
+}
+
+/// Get the kind of hazard of a single character, if any.
+pub fn char_hazard(c: char) -> Option<HazardKind> {
+    match c {
+        '\u{061c}' | '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}' => {
+            Some(HazardKind::Bidi)
+        }
+        '\u{00ad}' | '\u{034f}' | '\u{115f}' | '\u{1160}' | '\u{17b4}' | '\u{17b5}'
+        | '\u{180e}' | '\u{200b}'..='\u{200d}' | '\u{2060}'..='\u{2064}' | '\u{3164}'
+        | '\u{feff}' | '\u{ffa0}' => Some(HazardKind::Invisible),
+        '\u{e0000}'..='\u{e007f}' => Some(HazardKind::Tag),
+        _ => None,
+    }
+}
+
+/// Matcher to find risky characters, usable with `grep_searcher::Searcher`.
+#[derive(Clone, Debug)]
+pub struct HazardMatcher {
+    /// Whether to report words that mix scripts.
+    mixed_script: bool,
+}
+
+impl HazardMatcher {
+    pub fn new(mixed_script: bool) -> Self {
+        HazardMatcher { mixed_script }
+    }
+
+    /// Find all hazards in `bytes`.
+    pub fn hazards(&self, bytes: &[u8]) -> Vec<Hazard> {
+        self.find_hazards(bytes, false)
+    }
+
+    fn find_hazards(&self, bytes: &[u8], first_only: bool) -> Vec<Hazard> {
+        let mut result = vec![];
+        let mut word = vec![];
+
+        for (start, end, c) in char_indices_lossy(bytes) {
+            if let Some(kind) = char_hazard(c) {
+                result.push(Hazard { start, end, ch: c, kind });
+                if first_only {
+                    break;
+                }
+                continue;
+            }
+
+            if !self.mixed_script {
+                continue;
+            }
+
+            if c.is_alphanumeric() || c.script() == Script::Inherited {
+                word.push(Hazard { start, end, ch: c, kind: HazardKind::MixedScript });
+                continue;
+            }
+
+            result.append(&mut mixed_script_chars(&word));
+            word.clear();
+            if first_only && !result.is_empty() {
+                break;
+            }
+        }
+        result.append(&mut mixed_script_chars(&word));
+
+        result.sort_by_key(|h| h.start);
+        if first_only {
+            result.truncate(1);
+        }
+
+        result
+    }
+}
+
+impl Matcher for HazardMatcher {
+    type Captures = NoCaptures;
+    type Error = NoError;
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, NoError> {
+        let hazard = self.find_hazards(&haystack[at..], true).into_iter().next();
+        Ok(hazard.map(|h| Match::new(at + h.start, at + h.end)))
+    }
+
+    fn new_captures(&self) -> Result<NoCaptures, NoError> {
+        Ok(NoCaptures::new())
+    }
+}
+
+/// Get the characters of `word` that are not in the dominant script of the word.
+fn mixed_script_chars(word: &[Hazard]) -> Vec<Hazard> {
+    let mut counts: Vec<(Script, usize)> = vec![];
+    let mut scripts: HashMap<usize, Script> = HashMap::new();
+
+    for (i, h) in word.iter().enumerate() {
+        let script = h.ch.script();
+        if matches!(script, Script::Common | Script::Inherited | Script::Unknown) {
+            continue;
+        }
+        scripts.insert(i, script);
+
+        match counts.iter_mut().find(|(s, _)| *s == script) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((script, 1)),
+        }
+    }
+
+    if counts.len() < 2 {
+        return vec![];
+    }
+
+    let is_allowed = ALLOWED_SCRIPT_SETS
+        .iter()
+        .any(|set| counts.iter().all(|(s, _)| set.contains(s)));
+    if is_allowed {
+        return vec![];
+    }
+
+    // the first script wins a tie.
+    let mut dominant = counts[0];
+    for c in &counts[1..] {
+        if c.1 > dominant.1 {
+            dominant = *c;
+        }
+    }
+
+    word.iter()
+        .enumerate()
+        .filter(|(i, _)| matches!(scripts.get(i), Some(s) if *s != dominant.0))
+        .map(|(_, h)| h.clone())
+        .collect()
+}
+
+/// Sink to write hazards as `path:line:column:U+XXXX NAME (kind)`.
+/// The column is the 1-based position of the character in the line, counted in characters.
+struct HazardSink<'a> {
+    matcher: &'a HazardMatcher,
+    path: &'a Path,
+    output: String,
+}
+
+impl<'a> Sink for HazardSink<'a> {
+    type Error = io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch) -> Result<bool, Self::Error> {
+        let line_number = mat.line_number().unwrap_or(0);
+        let line = mat.bytes();
+
+        for hazard in self.matcher.hazards(line) {
+            // BOM at the start of the file is not a hazard.
+            if line_number == 1 && hazard.start == 0 && hazard.ch == '\u{feff}' {
+                continue;
+            }
+
+            let column = char_indices_lossy(&line[..hazard.start]).len() + 1;
+            let _ = writeln!(
+                self.output,
+                "{}:{}:{}:U+{:04X} {} ({})",
+                self.path.display(),
+                line_number,
+                column,
+                hazard.ch as u32,
+                hazard.name(),
+                hazard.kind.as_str(),
+            );
+        }
+
+        Ok(true)
+    }
+
+    fn binary_data(&mut self, _searcher: &Searcher, _binary_byte_offset: u64) -> Result<bool, Self::Error> {
+        // a binary file, such as an image or an executable, is not reported.
+        self.output.clear();
+        Ok(false)
+    }
+}
+
+/// Scan `path_list` and print every risky character found.
+/// Like hmgrep, a file with NUL is binary and skipped, unless it is UTF-16 with a BOM.
+/// The errors are printed to stderr, and the search goes on.
+pub fn scan_hazards(config: Config, matcher: HazardMatcher, path_list: Vec<PathBuf>) -> ScanStatus {
+    let mut config = config;
+    config.binary(true).bom_sniffing(true);
+
+    let is_found = Arc::new(AtomicBool::new(false));
+    let is_error = Arc::new(AtomicBool::new(false));
+
+    let walker = config.build_walker(path_list);
+
+    walker.unwrap().run(|| {
+        let matcher = matcher.clone();
+        let is_found = is_found.clone();
+        let is_error = is_error.clone();
+        let mut searcher = config.build_searcher();
+
+        Box::new(move |entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    eprintln!("hmscan: {}", e);
+                    is_error.store(true, Ordering::SeqCst);
+                    return ignore::WalkState::Continue;
+                }
+            };
+
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                return ignore::WalkState::Continue;
+            }
+
+            let mut sink = HazardSink {
+                matcher: &matcher,
+                path: entry.path(),
+                output: String::new(),
+            };
+
+            if let Err(e) = searcher.search_path(&matcher, entry.path(), &mut sink) {
+                eprintln!("hmscan: {}: {}", entry.path().display(), e);
+                is_error.store(true, Ordering::SeqCst);
+            }
+            if !sink.output.is_empty() {
+                is_found.store(true, Ordering::SeqCst);
+                print!("{}", sink.output);
+            }
+            ignore::WalkState::Continue
+        })
+    });
+
+    ScanStatus {
+        matched: is_found.load(Ordering::SeqCst),
+        error: is_error.load(Ordering::SeqCst),
+        ..ScanStatus::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_trojan_source_hazards() {
+        let matcher = HazardMatcher::new(false);
+        let line = "/* \u{202e} } \u{2066}if (isAdmin)\u{2069} \u{2066} begin admins only */";
+        let kinds: Vec<HazardKind> = matcher.hazards(line.as_bytes()).iter().map(|h| h.kind).collect();
+        assert_eq!(kinds, vec![HazardKind::Bidi; 4]);
+
+        let hazards = matcher.hazards("pay\u{200b}pal".as_bytes());
+        assert_eq!(hazards.len(), 1);
+        assert_eq!(hazards[0].start, 3);
+        assert_eq!(hazards[0].name(), "ZERO WIDTH SPACE");
+    }
+
+    #[test]
+    fn tests_mixed_script_hazards() {
+        let matcher = HazardMatcher::new(true);
+
+        // Cyrillic `а` in a Latin word.
+        let hazards = matcher.hazards("login to p\u{0430}ypal".as_bytes());
+        assert_eq!(hazards.len(), 1);
+        assert_eq!(hazards[0].ch, '\u{0430}');
+        assert_eq!(hazards[0].kind, HazardKind::MixedScript);
+
+        // Japanese text mixes Han, Hiragana and Katakana normally.
+        assert!(matcher.hazards("漢字とカタカナのtext".as_bytes()).is_empty());
+    }
+
+    #[test]
+    fn tests_hazard_sink_columns() {
+        let matcher = HazardMatcher::new(false);
+        let mut sink = HazardSink {
+            matcher: &matcher,
+            path: Path::new("a.txt"),
+            output: String::new(),
+        };
+
+        let mut searcher = grep_searcher::SearcherBuilder::new().line_number(true).build();
+        searcher
+            .search_slice(&matcher, "ok\nhéllo \u{200b}x\n".as_bytes(), &mut sink)
+            .unwrap();
+
+        // `é` is 2 bytes, but the column counts it as one character.
+        assert_eq!(sink.output, "a.txt:2:7:U+200B ZERO WIDTH SPACE (invisible)\n");
+    }
+}
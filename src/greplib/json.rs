@@ -0,0 +1,318 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+// Reference:
+//   - https://docs.rs/grep-printer/latest/grep_printer/struct.JSON.html (format of ripgrep)
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use grep_searcher::{Searcher, Sink, SinkContext, SinkFinish, SinkMatch};
+use serde::Serialize;
+
+use super::common::match_lines;
+use super::explain::Explainer;
+use super::matcher::HmgMatcher;
+
+/// A text field, as `{"text": "..."}` like ripgrep.
+/// Invalid UTF-8 is replaced with U+FFFD.
+#[derive(Serialize)]
+struct Text {
+    text: String,
+}
+
+impl Text {
+    fn new(bytes: &[u8]) -> Self {
+        Text {
+            text: String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+}
+
+/// A JSON Lines record. Each file with a match is printed as `begin`, `match`/`context`... and `end`.
+#[derive(Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "lowercase")]
+enum Record {
+    Begin {
+        path: Option<Text>,
+    },
+    Match {
+        path: Option<Text>,
+        lines: Text,
+        line_number: Option<u64>,
+        absolute_offset: u64,
+        submatches: Vec<SubMatch>,
+    },
+    Context {
+        path: Option<Text>,
+        lines: Text,
+        line_number: Option<u64>,
+        absolute_offset: u64,
+    },
+    End {
+        path: Option<Text>,
+        stats: Stats,
+    },
+}
+
+#[derive(Serialize)]
+struct SubMatch {
+    #[serde(rename = "match")]
+    text: Text,
+
+    /// Byte offsets in `lines`.
+    start: usize,
+    end: usize,
+
+    /// The pattern that matched, and the error count of approximate matching.
+    pattern: Option<String>,
+    errors: Option<u32>,
+
+    /// With `--json-field`, the field that matched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<String>,
+
+    /// The pattern character of each matched character.
+    mapping: Vec<Mapping>,
+}
+
+#[derive(Serialize)]
+struct Mapping {
+    char: char,
+    code_point: String,
+    pattern_char: Option<char>,
+    substituted: bool,
+}
+
+#[derive(Serialize)]
+struct Stats {
+    matched_lines: u64,
+    matches: u64,
+}
+
+/// Printer of JSON Lines, for the tools that ingest the matches.
+pub struct JsonPrinter<'a> {
+    matcher: &'a HmgMatcher,
+    explainer: Option<&'a Explainer>,
+    path: Option<PathBuf>,
+
+    /// The maximum number of matching lines to print.
+    max_matches: Option<u64>,
+    stats: Stats,
+
+    /// Whether the `begin` record is printed.
+    is_begun: bool,
+    wtr: Vec<u8>,
+}
+
+impl<'a> JsonPrinter<'a> {
+    pub fn new(matcher: &'a HmgMatcher) -> Self {
+        JsonPrinter {
+            matcher,
+            explainer: None,
+            path: None,
+            max_matches: None,
+            stats: Stats {
+                matched_lines: 0,
+                matches: 0,
+            },
+            is_begun: false,
+            wtr: vec![],
+        }
+    }
+
+    pub fn path(&mut self, path: &Path) -> &mut Self {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Stop the search after `limit` matching lines.
+    pub fn max_matches(&mut self, limit: Option<u64>) -> &mut Self {
+        self.max_matches = limit;
+        self
+    }
+
+    /// Map the matched characters to the pattern characters with `explainer`.
+    pub fn explainer(&mut self, explainer: &'a Explainer) -> &mut Self {
+        self.explainer = Some(explainer);
+        self
+    }
+
+    /// Whether any line has matched.
+    pub fn has_matched(&self) -> bool {
+        self.stats.matched_lines > 0
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.wtr
+    }
+
+    fn path_text(&self) -> Option<Text> {
+        self.path.as_ref().map(|p| Text::new(p.to_string_lossy().as_bytes()))
+    }
+
+    fn write_record(&mut self, record: &Record) -> io::Result<()> {
+        serde_json::to_writer(&mut self.wtr, record)?;
+        self.wtr.write_all(b"\n")
+    }
+
+    /// Print the `begin` record before the first line of the file.
+    fn write_begin(&mut self) -> io::Result<()> {
+        if self.is_begun {
+            return Ok(());
+        }
+        self.is_begun = true;
+
+        let record = Record::Begin { path: self.path_text() };
+        self.write_record(&record)
+    }
+
+    /// The pattern of a match. Without the pattern index, it is the only pattern.
+    fn pattern(&self, index: Option<usize>) -> Option<String> {
+        if let Some(label) = index.and_then(|i| self.matcher.pattern_label(i)) {
+            return Some(label.to_string());
+        }
+
+        match self.explainer.map(|e| e.patterns()) {
+            Some([pattern]) => Some(pattern.clone()),
+            _ => None,
+        }
+    }
+
+    fn submatches(&self, line: &[u8]) -> Vec<SubMatch> {
+        let mut result = vec![];
+
+        for m in self.matcher.find_all(line) {
+            let text = &line[m.start..m.end];
+            let mapping = match self.explainer {
+                Some(explainer) => explainer
+                    .mapping(&self.matcher.decode(text))
+                    .into_iter()
+                    .map(|c| Mapping {
+                        char: c.ch,
+                        code_point: format!("U+{:04X}", c.ch as u32),
+                        pattern_char: c.pattern_char,
+                        substituted: c.substituted,
+                    })
+                    .collect(),
+                None => vec![],
+            };
+
+            result.push(SubMatch {
+                text: Text::new(text),
+                start: m.start,
+                end: m.end,
+                pattern: self.pattern(m.pattern),
+                errors: m.errors,
+                field: m.field.and_then(|i| self.matcher.field_label(i)).map(|f| f.to_string()),
+                mapping,
+            });
+        }
+
+        result
+    }
+}
+
+impl<'a> Sink for JsonPrinter<'a> {
+    type Error = io::Error;
+
+    fn matched(&mut self, searcher: &Searcher, mat: &SinkMatch) -> Result<bool, io::Error> {
+        self.write_begin()?;
+
+        let mut line_number = mat.line_number();
+        let mut offset = mat.absolute_byte_offset();
+
+        for line in match_lines(searcher, self.matcher, mat) {
+            if self.max_matches.is_some_and(|limit| self.stats.matched_lines >= limit) {
+                break;
+            }
+
+            let submatches = self.submatches(line);
+            self.stats.matched_lines += 1;
+            self.stats.matches += submatches.len() as u64;
+
+            let record = Record::Match {
+                path: self.path_text(),
+                lines: Text::new(line),
+                line_number,
+                absolute_offset: offset,
+                submatches,
+            };
+            self.write_record(&record)?;
+
+            line_number = line_number.map(|n| n + 1);
+            offset += line.len() as u64;
+        }
+
+        // returning false stops the search of this file.
+        Ok(self.max_matches.is_none_or(|limit| self.stats.matched_lines < limit))
+    }
+
+    fn context(&mut self, _searcher: &Searcher, context: &SinkContext) -> Result<bool, io::Error> {
+        self.write_begin()?;
+
+        let record = Record::Context {
+            path: self.path_text(),
+            lines: Text::new(context.bytes()),
+            line_number: context.line_number(),
+            absolute_offset: context.absolute_byte_offset(),
+        };
+        self.write_record(&record)?;
+
+        Ok(true)
+    }
+
+    fn finish(&mut self, _searcher: &Searcher, _finish: &SinkFinish) -> Result<(), io::Error> {
+        if !self.is_begun {
+            return Ok(());
+        }
+
+        let stats = Stats {
+            matched_lines: self.stats.matched_lines,
+            matches: self.stats.matches,
+        };
+        let record = Record::End {
+            path: self.path_text(),
+            stats,
+        };
+        self.write_record(&record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use grep_searcher::SearcherBuilder;
+    use hmglib::Homoglyphs;
+
+    use super::super::skeleton::SkeletonMatcher;
+
+    #[test]
+    fn tests_json_mapping() {
+        let homoglyphs = Homoglyphs::new();
+        let patterns = vec!["ちんこ".to_string()];
+        let matcher = HmgMatcher::Skeleton(SkeletonMatcher::new(&homoglyphs, &patterns[0]));
+        let explainer = Explainer::new(&homoglyphs, &patterns);
+
+        let mut printer = JsonPrinter::new(&matcher);
+        printer.explainer(&explainer);
+        let mut searcher = SearcherBuilder::new().line_number(true).build();
+        searcher
+            .search_slice(&matcher, "foo\nxㄘんこ\n".as_bytes(), &mut printer)
+            .unwrap();
+
+        let output = String::from_utf8(printer.into_inner()).unwrap();
+        let records: Vec<serde_json::Value> = output.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(records.len(), 3);
+
+        let data = &records[1]["data"];
+        assert_eq!(data["line_number"], 2);
+        assert_eq!(data["submatches"][0]["pattern"], "ちんこ");
+        assert_eq!(data["submatches"][0]["mapping"][0]["code_point"], "U+3118");
+        assert_eq!(data["submatches"][0]["mapping"][0]["pattern_char"], "ち");
+        assert_eq!(data["submatches"][0]["mapping"][0]["substituted"], true);
+        assert_eq!(records[2]["data"]["stats"]["matched_lines"], 1);
+    }
+}
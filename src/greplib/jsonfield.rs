@@ -0,0 +1,429 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+// Reference:
+//   - https://www.rfc-editor.org/rfc/rfc8259 (JSON)
+//   - https://jsonlines.org/
+
+use std::error::Error;
+use std::fmt;
+
+use grep_matcher::{Match, Matcher, NoCaptures, NoError};
+
+use super::common::find_in_records;
+use super::matcher::{HmgMatch, HmgMatcher};
+
+/// A segment of a field path.
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// A path to a field of a JSON record, like `.user.name` or `.items[0]`.
+/// `.` is the whole record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldPath {
+    /// The path as given, shown in the output.
+    pub label: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug)]
+pub struct FieldPathError(String);
+
+impl fmt::Display for FieldPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid field path: {}", self.0)
+    }
+}
+
+impl Error for FieldPathError {}
+
+impl FieldPath {
+    /// Parse `.key`, `."quoted key"` and `[index]` segments.
+    pub fn parse(text: &str) -> Result<Self, FieldPathError> {
+        let error = || FieldPathError(text.to_string());
+        if !text.starts_with('.') && !text.starts_with('[') {
+            return Err(error());
+        }
+
+        let mut segments = vec![];
+        let mut rest = text;
+        while !rest.is_empty() {
+            if let Some(r) = rest.strip_prefix('[') {
+                let end = r.find(']').ok_or_else(error)?;
+                segments.push(Segment::Index(r[..end].parse().map_err(|_| error())?));
+                rest = &r[end + 1..];
+            } else if let Some(r) = rest.strip_prefix(".\"") {
+                let end = r.find('"').ok_or_else(error)?;
+                segments.push(Segment::Key(r[..end].to_string()));
+                rest = &r[end + 1..];
+            } else if let Some(r) = rest.strip_prefix('.') {
+                let end = r.find(['.', '[']).unwrap_or(r.len());
+                if end > 0 {
+                    segments.push(Segment::Key(r[..end].to_string()));
+                } else if !r.is_empty() {
+                    return Err(error());
+                }
+                rest = &r[end..];
+            } else {
+                return Err(error());
+            }
+        }
+
+        Ok(FieldPath {
+            label: text.to_string(),
+            segments,
+        })
+    }
+
+    /// Whether a value at `path` is in this field. The strings in an object or an array are all in it.
+    fn contains(&self, path: &[Segment]) -> bool {
+        path.starts_with(&self.segments)
+    }
+}
+
+/// A decoded string value of a field, with a map back to the offsets of the raw line.
+#[derive(Clone, Debug)]
+struct FieldValue {
+    /// The index of the field path.
+    field: usize,
+    text: String,
+
+    /// Offset in the raw line of each byte of `text` (and `text.len()`).
+    offsets: Vec<usize>,
+}
+
+/// Matcher that searches the string values of the given fields of each JSON line.
+/// The strings are decoded, so `а` is matched as `а`. The matches are the ranges of the raw strings.
+/// Lines that are not JSON do not match.
+#[derive(Clone, Debug)]
+pub struct JsonFieldMatcher {
+    matcher: Box<HmgMatcher>,
+    fields: Vec<FieldPath>,
+}
+
+impl JsonFieldMatcher {
+    pub fn new(matcher: HmgMatcher, fields: Vec<FieldPath>) -> Self {
+        JsonFieldMatcher {
+            matcher: Box::new(matcher),
+            fields,
+        }
+    }
+
+    /// The matcher of the decoded strings.
+    pub fn inner(&self) -> &HmgMatcher {
+        &self.matcher
+    }
+
+    pub fn inner_mut(&mut self) -> &mut HmgMatcher {
+        &mut self.matcher
+    }
+
+    /// Get the label of the field of `HmgMatch::field`.
+    pub fn field_label(&self, index: usize) -> Option<&str> {
+        self.fields.get(index).map(|f| f.label.as_str())
+    }
+
+    /// Find all matches in the fields of `line`, as ranges of the raw line.
+    pub fn find_all(&self, line: &[u8]) -> Vec<HmgMatch> {
+        let mut result = vec![];
+
+        for value in self.field_values(line) {
+            for mut m in self.matcher.find_all(value.text.as_bytes()) {
+                m.start = value.offsets[m.start];
+                m.end = value.offsets[m.end];
+                m.field = Some(value.field);
+                result.push(m);
+            }
+        }
+        result.sort_by_key(|m| (m.start, usize::MAX - m.end));
+
+        result
+    }
+
+    fn field_values(&self, line: &[u8]) -> Vec<FieldValue> {
+        let mut result = vec![];
+        let mut scanner = Scanner { bytes: line, pos: 0 };
+
+        scanner.value(&mut vec![], &mut |path, text, offsets| {
+            if let Some(field) = self.fields.iter().position(|f| f.contains(path)) {
+                result.push(FieldValue { field, text, offsets });
+            }
+        });
+
+        result
+    }
+}
+
+impl Matcher for JsonFieldMatcher {
+    type Captures = NoCaptures;
+    type Error = NoError;
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, NoError> {
+        // a record is a whole line, so parse from the start of the line of `at`.
+        let result = find_in_records(haystack, at, |line, min_start| {
+            let m = self.find_all(line).into_iter().find(|m| m.start >= min_start)?;
+            Some((m.start, m.end))
+        });
+
+        Ok(result.map(|(s, e)| Match::new(s, e)))
+    }
+
+    fn new_captures(&self) -> Result<NoCaptures, NoError> {
+        Ok(NoCaptures::new())
+    }
+}
+
+/// Decode the content of a raw JSON string (without the quotes), such as a match of `JsonFieldMatcher`.
+pub fn decode_string(raw: &[u8]) -> String {
+    let mut quoted = Vec::with_capacity(raw.len() + 2);
+    quoted.push(b'"');
+    quoted.extend_from_slice(raw);
+    quoted.push(b'"');
+
+    let mut scanner = Scanner { bytes: &quoted, pos: 0 };
+    match scanner.string() {
+        Some((text, _)) => text,
+        None => String::from_utf8_lossy(raw).into_owned(),
+    }
+}
+
+/// A minimal JSON scanner that keeps the offsets of the strings.
+/// A broken record is scanned as far as it is valid.
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\r' | b'\n') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, b: u8) -> Option<()> {
+        self.skip_whitespace();
+        if self.peek() != Some(b) {
+            return None;
+        }
+        self.pos += 1;
+        Some(())
+    }
+
+    /// Scan a value at `path`, and call `visit` with each string value.
+    fn value<F>(&mut self, path: &mut Vec<Segment>, visit: &mut F) -> Option<()>
+    where
+        F: FnMut(&[Segment], String, Vec<usize>),
+    {
+        self.skip_whitespace();
+
+        match self.peek()? {
+            b'{' => {
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                    return Some(());
+                }
+                loop {
+                    self.skip_whitespace();
+                    let (key, _) = self.string()?;
+                    self.expect(b':')?;
+
+                    path.push(Segment::Key(key));
+                    let result = self.value(path, visit);
+                    path.pop();
+                    result?;
+
+                    self.skip_whitespace();
+                    match self.peek()? {
+                        b',' => self.pos += 1,
+                        b'}' => {
+                            self.pos += 1;
+                            return Some(());
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    return Some(());
+                }
+                let mut index = 0;
+                loop {
+                    path.push(Segment::Index(index));
+                    let result = self.value(path, visit);
+                    path.pop();
+                    result?;
+
+                    self.skip_whitespace();
+                    match self.peek()? {
+                        b',' => self.pos += 1,
+                        b']' => {
+                            self.pos += 1;
+                            return Some(());
+                        }
+                        _ => return None,
+                    }
+                    index += 1;
+                }
+            }
+            b'"' => {
+                let (text, offsets) = self.string()?;
+                visit(path, text, offsets);
+                Some(())
+            }
+            _ => {
+                // numbers, true, false and null.
+                let start = self.pos;
+                while let Some(b) = self.peek() {
+                    if matches!(b, b',' | b']' | b'}' | b' ' | b'\t' | b'\r' | b'\n') {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                if self.pos == start {
+                    return None;
+                }
+                Some(())
+            }
+        }
+    }
+
+    /// Scan a string, and get the decoded text and the offset of each byte of it.
+    fn string(&mut self) -> Option<(String, Vec<usize>)> {
+        if self.peek()? != b'"' {
+            return None;
+        }
+        self.pos += 1;
+
+        let mut text = String::new();
+        let mut offsets = vec![];
+
+        loop {
+            let start = self.pos;
+            let c = match self.peek()? {
+                b'"' => {
+                    offsets.push(self.pos);
+                    self.pos += 1;
+                    return Some((text, offsets));
+                }
+                b'\\' => {
+                    self.pos += 1;
+                    let escaped = self.peek()?;
+                    self.pos += 1;
+                    match escaped {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return None,
+                    }
+                }
+                _ => {
+                    // a raw character. invalid UTF-8 is replaced.
+                    let rest = &self.bytes[self.pos..];
+                    let len = utf8_len(rest[0]).min(rest.len());
+                    match std::str::from_utf8(&rest[..len]).ok().and_then(|s| s.chars().next()) {
+                        Some(c) => {
+                            self.pos += len;
+                            c
+                        }
+                        None => {
+                            self.pos += 1;
+                            '\u{fffd}'
+                        }
+                    }
+                }
+            };
+
+            for _ in 0..c.len_utf8() {
+                offsets.push(start);
+            }
+            text.push(c);
+        }
+    }
+
+    /// Decode the `XXXX` of `\uXXXX`, and the low surrogate that follows a high surrogate.
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return Some(char::from_u32(high).unwrap_or('\u{fffd}'));
+        }
+
+        if self.bytes.get(self.pos..self.pos + 2) != Some(b"\\u") {
+            return Some('\u{fffd}');
+        }
+        self.pos += 2;
+        let low = self.hex4()?;
+        let c = 0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+        Some(char::from_u32(c).unwrap_or('\u{fffd}'))
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let hex = self.bytes.get(self.pos..self.pos + 4)?;
+        let value = u32::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?;
+        self.pos += 4;
+        Some(value)
+    }
+}
+
+/// The length of the UTF-8 sequence that starts with `b`.
+fn utf8_len(b: u8) -> usize {
+    match b {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hmglib::Homoglyphs;
+
+    use super::super::skeleton::SkeletonMatcher;
+
+    #[test]
+    fn tests_json_field_matcher() {
+        assert!(FieldPath::parse("message").is_err());
+        assert_eq!(FieldPath::parse(".user.\"full.name\"[1]").unwrap().segments.len(), 3);
+
+        let homoglyphs = Homoglyphs::new();
+        let matcher = HmgMatcher::Skeleton(SkeletonMatcher::new(&homoglyphs, "paypal"));
+        let fields = vec![FieldPath::parse(".message").unwrap(), FieldPath::parse(".user").unwrap()];
+        let matcher = JsonFieldMatcher::new(matcher, fields);
+
+        // `\u0430` is the Cyrillic `а`. the key `paypal` is not a field value.
+        let line = r#"{"paypal": 1, "message": "login p\u0430ypal", "user": {"name": "paypal"}}"#.as_bytes();
+        let matches = matcher.find_all(line);
+        assert_eq!(matches.len(), 2);
+
+        assert_eq!(&line[matches[0].start..matches[0].end], br"p\u0430ypal");
+        assert_eq!(matches[0].field, Some(0));
+        assert_eq!(decode_string(&line[matches[0].start..matches[0].end]), "p\u{430}ypal");
+        assert_eq!(matches[1].field, Some(1));
+
+        assert!(matcher.find_all(b"paypal").is_empty());
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+// Reference:
+//   - https://www.rfc-editor.org/rfc/rfc5322 (Internet Message Format)
+//   - https://www.rfc-editor.org/rfc/rfc2047 (encoded-words)
+//   - https://www.rfc-editor.org/rfc/rfc4155 (mbox)
+
+use std::io;
+use std::path::Path;
+
+use mail_parser::mailbox::mbox::MessageIterator;
+use mail_parser::{Address, HeaderValue, Message, MessageParser};
+
+use super::document::Part;
+
+/// The headers that are searched. The others are IDs, dates and transport information.
+const SEARCHED_HEADERS: [&str; 10] = [
+    "From", "Sender", "Reply-To", "To", "Cc", "Bcc", "Subject", "Comments", "Keywords", "List-Id",
+];
+
+/// The format of a mail file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MailKind {
+    /// A single message.
+    Eml,
+
+    /// Messages each of which starts with a `From ` line.
+    Mbox,
+}
+
+impl MailKind {
+    /// Get the kind by the extension of `path`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "eml" => Some(MailKind::Eml),
+            "mbox" | "mbx" => Some(MailKind::Mbox),
+            _ => None,
+        }
+    }
+
+    /// Guess the kind of `data`, such as a file without an extension given with `--mail`.
+    pub fn sniff(data: &[u8]) -> Self {
+        if data.starts_with(b"From ") {
+            MailKind::Mbox
+        } else {
+            MailKind::Eml
+        }
+    }
+}
+
+/// The searched text of a message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mail {
+    /// The Message-ID, without `<>`.
+    pub id: Option<String>,
+
+    /// The decoded headers named by the header name, and the bodies named `body` (or `html`).
+    pub parts: Vec<Part>,
+}
+
+/// Extract the headers and the text bodies of the messages in `data`.
+/// The encoded-words, the transfer encodings and the charsets are decoded.
+pub fn extract(kind: MailKind, data: &[u8]) -> io::Result<Vec<Mail>> {
+    let parser = MessageParser::default();
+
+    match kind {
+        MailKind::Eml => Ok(parser.parse(data).map(|m| mail(&m)).into_iter().collect()),
+        MailKind::Mbox => {
+            let mut result = vec![];
+            for message in MessageIterator::new(data) {
+                let message =
+                    message.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid mbox message"))?;
+                if let Some(m) = parser.parse(message.contents()) {
+                    result.push(mail(&m));
+                }
+            }
+            Ok(result)
+        }
+    }
+}
+
+fn mail(message: &Message) -> Mail {
+    let mut parts = vec![];
+
+    for header in message.headers() {
+        let name = header.name();
+        if !SEARCHED_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+            continue;
+        }
+
+        let text = match header.value() {
+            HeaderValue::Text(text) => text.to_string(),
+            HeaderValue::TextList(list) => list.join(", "),
+            HeaderValue::Address(address) => address_text(address),
+            _ => continue,
+        };
+        parts.push(Part {
+            name: Some(name.to_string()),
+            text,
+        });
+    }
+
+    // each body is searched once. the text body of a multipart/alternative message is the plain
+    // text part, and the one of an HTML-only message is the HTML part, which is searched as is, also for the links.
+    for part in message.text_bodies() {
+        let name = if part.is_text_html() { "html" } else { "body" };
+        if let Some(text) = part.text_contents() {
+            parts.push(Part {
+                name: Some(name.to_string()),
+                text: text.to_string(),
+            });
+        }
+    }
+
+    Mail {
+        id: message.message_id().map(|id| id.to_string()),
+        parts,
+    }
+}
+
+/// Format addresses as `Name <address>, ...`.
+fn address_text(address: &Address) -> String {
+    let addr_text = |name: Option<&str>, address: Option<&str>| match (name, address) {
+        (Some(name), Some(address)) => format!("{} <{}>", name, address),
+        (Some(name), None) => name.to_string(),
+        (None, address) => address.unwrap_or_default().to_string(),
+    };
+
+    match address {
+        Address::List(list) => list
+            .iter()
+            .map(|a| addr_text(a.name(), a.address()))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Address::Group(groups) => groups
+            .iter()
+            .map(|g| {
+                let members: Vec<String> = g.addresses.iter().map(|a| addr_text(a.name(), a.address())).collect();
+                format!("{}: {}", g.name.as_deref().unwrap_or_default(), members.join(", "))
+            })
+            .collect::<Vec<_>>()
+            .join("; "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_extract_mbox() {
+        let data = concat!(
+            "From alice@example.com Mon Jan  1 00:00:00 2024\n",
+            "From: =?UTF-8?B?cNCweXBhbA==?= <support@example.com>\n",
+            "Subject: =?UTF-8?Q?Verify_your_p=D0=B0ypal_account?=\n",
+            "Message-ID: <1@example.com>\n",
+            "Content-Type: text/plain; charset=utf-8\n",
+            "Content-Transfer-Encoding: quoted-printable\n",
+            "\n",
+            "Log in to p=D0=B0ypal.\n",
+            "\n",
+            "From bob@example.com Mon Jan  1 00:00:00 2024\n",
+            "Subject: hello\n",
+            "\n",
+            "hi\n",
+        );
+
+        let mails = extract(MailKind::sniff(data.as_bytes()), data.as_bytes()).unwrap();
+        assert_eq!(mails.len(), 2);
+        assert_eq!(mails[0].id.as_deref(), Some("1@example.com"));
+        assert_eq!(mails[1].id, None);
+
+        let part = |name: &str| mails[0].parts.iter().find(|p| p.name.as_deref() == Some(name)).unwrap();
+        assert_eq!(part("From").text, "p\u{430}ypal <support@example.com>");
+        assert_eq!(part("Subject").text, "Verify your p\u{430}ypal account");
+        assert_eq!(part("body").text.trim_end(), "Log in to p\u{430}ypal.");
+    }
+
+    #[test]
+    fn tests_extract_html_only() {
+        let data = concat!(
+            "Subject: hello\n",
+            "Content-Type: text/html; charset=utf-8\n",
+            "\n",
+            "<p>Log in to <a href=\"https://example.com\">p\u{430}ypal</a>.</p>\n",
+        );
+
+        let mails = extract(MailKind::Eml, data.as_bytes()).unwrap();
+        let names: Vec<_> = mails[0].parts.iter().map(|p| p.name.as_deref().unwrap()).collect();
+        assert_eq!(names, vec!["Subject", "html"]);
+    }
+
+    #[test]
+    fn tests_extract_alternative() {
+        let data = concat!(
+            "Subject: hello\n",
+            "Content-Type: multipart/alternative; boundary=b\n",
+            "\n",
+            "--b\n",
+            "Content-Type: text/plain; charset=utf-8\n",
+            "\n",
+            "Log in to p\u{430}ypal.\n",
+            "--b\n",
+            "Content-Type: text/html; charset=utf-8\n",
+            "\n",
+            "<p>Log in to p\u{430}ypal.</p>\n",
+            "--b--\n",
+        );
+
+        let mails = extract(MailKind::Eml, data.as_bytes()).unwrap();
+        let names: Vec<_> = mails[0].parts.iter().map(|p| p.name.as_deref().unwrap()).collect();
+        assert_eq!(names, vec!["Subject", "body"]);
+    }
+}
@@ -0,0 +1,239 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+use std::borrow::Cow;
+
+use grep_matcher::{Match, Matcher, NoCaptures, NoError};
+use grep_regex::RegexMatcher;
+
+use super::approx::ApproxMatcher;
+use super::blocklist::Blocklist;
+use super::csvcolumn::{self, CsvColumnMatcher};
+use super::decode::{self, DecodeMatcher};
+use super::jsonfield::{self, JsonFieldMatcher};
+use super::skeleton::SkeletonMatcher;
+
+/// A match found by `HmgMatcher`, with the extra information shown by `AnnotatedPrinter`.
+#[derive(Clone, Copy, Debug)]
+pub struct HmgMatch {
+    pub start: usize,
+    pub end: usize,
+
+    /// The error count of an approximate match.
+    pub errors: Option<u32>,
+
+    /// The index of the pattern in `HmgMatcher::Patterns`.
+    pub pattern: Option<usize>,
+
+    /// The index of the field path in `HmgMatcher::JsonField`, or the column in `HmgMatcher::CsvColumn`.
+    pub field: Option<usize>,
+}
+
+/// A pattern of `HmgMatcher::Patterns`, with the label shown in the output.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    /// The original pattern text.
+    pub label: String,
+
+    pub matcher: HmgMatcher,
+}
+
+/// The search engine used by `scan`.
+#[derive(Clone, Debug)]
+pub enum HmgMatcher {
+    /// Alternation regex generated by `generate_pattern_regex`.
+    Regex(RegexMatcher),
+
+    /// Confusable skeleton comparison. Used for long patterns.
+    Skeleton(SkeletonMatcher),
+
+    /// Approximate (edit-distance) matching.
+    Approx(ApproxMatcher),
+
+    /// Multiple patterns. Matches are labeled with the pattern that matched.
+    Patterns(Vec<Pattern>),
+
+    /// A large list of words compiled into a single automaton.
+    /// Matches are labeled with the entry that matched.
+    Blocklist(Blocklist),
+
+    /// Another matcher applied to the decoded string fields of JSON lines.
+    /// Matches are labeled with the field that matched.
+    JsonField(JsonFieldMatcher),
+
+    /// Another matcher applied to the given columns of CSV records.
+    /// Matches are labeled with the column that matched.
+    CsvColumn(CsvColumnMatcher),
+
+    /// Another matcher applied to the lines decoded with `--decode`.
+    Decode(DecodeMatcher),
+}
+
+impl HmgMatcher {
+    /// Whether the matches have information that only `AnnotatedPrinter` can show.
+    pub fn is_annotated(&self) -> bool {
+        match self {
+            HmgMatcher::Decode(m) => m.inner().is_annotated(),
+            _ => matches!(
+                self,
+                HmgMatcher::Approx(_) | HmgMatcher::Patterns(_) | HmgMatcher::Blocklist(_) | HmgMatcher::JsonField(_)
+                    | HmgMatcher::CsvColumn(_)
+            ),
+        }
+    }
+
+    /// Get the label of the pattern of `HmgMatch::pattern`.
+    pub fn pattern_label(&self, index: usize) -> Option<&str> {
+        match self {
+            HmgMatcher::Patterns(patterns) => patterns.get(index).map(|p| p.label.as_str()),
+            HmgMatcher::Blocklist(m) => m.entry(index),
+            HmgMatcher::JsonField(m) => m.inner().pattern_label(index),
+            HmgMatcher::CsvColumn(m) => m.inner().pattern_label(index),
+            HmgMatcher::Decode(m) => m.inner().pattern_label(index),
+            _ => None,
+        }
+    }
+
+    /// Get the label of the field or the column of `HmgMatch::field`.
+    pub fn field_label(&self, index: usize) -> Option<&str> {
+        match self {
+            HmgMatcher::JsonField(m) => m.field_label(index),
+            HmgMatcher::CsvColumn(m) => m.column_label(index),
+            HmgMatcher::Decode(m) => m.inner().field_label(index),
+            _ => None,
+        }
+    }
+
+    /// Get the text that was matched in `text`, the bytes of a match.
+    /// It differs from `text` only for `JsonField`, `CsvColumn` and `Decode`, whose matches are raw JSON strings,
+    /// CSV cells and encoded text.
+    pub fn decode<'a>(&self, text: &'a [u8]) -> Cow<'a, [u8]> {
+        match self {
+            HmgMatcher::JsonField(m) => {
+                let text = jsonfield::decode_string(text);
+                Cow::Owned(m.inner().decode(text.as_bytes()).into_owned())
+            }
+            HmgMatcher::CsvColumn(m) => {
+                let text = csvcolumn::decode_cell(text);
+                Cow::Owned(m.inner().decode(text.as_bytes()).into_owned())
+            }
+            HmgMatcher::Decode(m) => {
+                let decoded = decode::decode(text, m.decodings());
+                Cow::Owned(m.inner().decode(&decoded.bytes).into_owned())
+            }
+            _ => Cow::Borrowed(text),
+        }
+    }
+
+    /// Set the maximum error count of approximate matching.
+    pub fn max_errors(&mut self, num: u32) {
+        match self {
+            HmgMatcher::Approx(m) => {
+                m.max_errors(num);
+            }
+            HmgMatcher::Patterns(patterns) => {
+                for p in patterns {
+                    p.matcher.max_errors(num);
+                }
+            }
+            HmgMatcher::JsonField(m) => m.inner_mut().max_errors(num),
+            HmgMatcher::CsvColumn(m) => m.inner_mut().max_errors(num),
+            HmgMatcher::Decode(m) => m.inner_mut().max_errors(num),
+            _ => {}
+        }
+    }
+
+    /// Find all matches in `line`.
+    pub fn find_all(&self, line: &[u8]) -> Vec<HmgMatch> {
+        let mut result = vec![];
+
+        match self {
+            HmgMatcher::Approx(m) => {
+                for am in m.find_all(line) {
+                    result.push(HmgMatch {
+                        start: am.start,
+                        end: am.end,
+                        errors: Some(am.errors),
+                        pattern: None,
+                        field: None,
+                    });
+                }
+            }
+            HmgMatcher::Patterns(patterns) => {
+                for (i, p) in patterns.iter().enumerate() {
+                    for mut m in p.matcher.find_all(line) {
+                        m.pattern = Some(i);
+                        result.push(m);
+                    }
+                }
+                result.sort_by_key(|m| (m.start, usize::MAX - m.end));
+            }
+            HmgMatcher::JsonField(m) => result = m.find_all(line),
+            HmgMatcher::CsvColumn(m) => result = m.find_all(line),
+            HmgMatcher::Decode(m) => result = m.find_all(line),
+            HmgMatcher::Blocklist(m) => {
+                for bm in m.find_all(line) {
+                    result.push(HmgMatch {
+                        start: bm.start,
+                        end: bm.end,
+                        errors: None,
+                        pattern: Some(bm.entry),
+                        field: None,
+                    });
+                }
+            }
+            _ => {
+                let _ = self.find_iter(line, |m| {
+                    result.push(HmgMatch {
+                        start: m.start(),
+                        end: m.end(),
+                        errors: None,
+                        pattern: None,
+                        field: None,
+                    });
+                    true
+                });
+            }
+        }
+
+        result
+    }
+}
+
+impl Matcher for HmgMatcher {
+    type Captures = NoCaptures;
+    type Error = NoError;
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, NoError> {
+        match self {
+            HmgMatcher::Regex(m) => m.find_at(haystack, at),
+            HmgMatcher::Skeleton(m) => m.find_at(haystack, at),
+            HmgMatcher::Approx(m) => m.find_at(haystack, at),
+            HmgMatcher::Blocklist(m) => m.find_at(haystack, at),
+            HmgMatcher::JsonField(m) => m.find_at(haystack, at),
+            HmgMatcher::CsvColumn(m) => m.find_at(haystack, at),
+            HmgMatcher::Decode(m) => m.find_at(haystack, at),
+            HmgMatcher::Patterns(patterns) => {
+                // the leftmost match. if same start, the longest one.
+                let mut result: Option<Match> = None;
+                for p in patterns {
+                    if let Some(m) = p.matcher.find_at(haystack, at)? {
+                        let is_better = match result {
+                            Some(r) => (m.start(), r.end()) < (r.start(), m.end()),
+                            None => true,
+                        };
+                        if is_better {
+                            result = Some(m);
+                        }
+                    }
+                }
+                Ok(result)
+            }
+        }
+    }
+
+    fn new_captures(&self) -> Result<NoCaptures, NoError> {
+        Ok(NoCaptures::new())
+    }
+}
@@ -3,19 +3,58 @@
 // that can be found in the LICENSE file.
 
 use std::cmp;
+use std::collections::BTreeMap;
 use std::error::Error;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::env;
+use std::fs::File;
+use std::io::{self, Cursor, IsTerminal, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
-use crossbeam::queue::ArrayQueue;
-use termcolor::{Ansi, NoColor, WriteColor};
+use crossbeam::channel;
+use termcolor::Buffer;
 
 // ripgrep modules
 use grep::matcher::LineTerminator;
-use grep::printer::{ColorSpecs, Standard, StandardBuilder};
+use grep::printer::{StandardBuilder, SummaryBuilder, SummaryKind};
 use grep_regex::{RegexMatcher, RegexMatcherBuilder};
-use grep_searcher::{BinaryDetection, MmapChoice, Searcher, SearcherBuilder, Sink, SinkMatch};
-use ignore::{DirEntry, Walk, WalkBuilder, WalkParallel};
+use grep_searcher::{BinaryDetection, Encoding, Searcher, SearcherBuilder, Sink, SinkError};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{Walk, WalkBuilder, WalkParallel};
+
+// local module
+pub mod approx;
+pub mod archive;
+pub mod blocklist;
+pub mod color;
+mod common;
+pub mod csvcolumn;
+pub mod decode;
+pub mod decompress;
+pub mod document;
+pub mod explain;
+pub mod hazard;
+pub mod json;
+pub mod jsonfield;
+pub mod mail;
+pub mod matcher;
+pub mod printer;
+pub mod sarif;
+pub mod skeleton;
+
+use self::approx::BestErrorsSink;
+use self::archive::{ArchiveKind, Limits};
+use self::color::Colors;
+use self::csvcolumn::CsvColumnMatcher;
+use self::document::DocumentKind;
+use self::explain::Explainer;
+use self::json::JsonPrinter;
+use self::mail::MailKind;
+use self::matcher::HmgMatcher;
+use self::printer::{AnnotatedPrinter, GroupBreakSink};
+use self::sarif::SarifPrinter;
 
 // const
 #[cfg(windows)]
@@ -24,6 +63,15 @@ const LINE_ENDING: u8 = b"\r\n";
 #[cfg(not(windows))]
 const LINE_ENDING: u8 = b'\n';
 
+/// The path that means stdin.
+const STDIN_PATH: &str = "-";
+
+/// The name of stdin in the output.
+const STDIN_LABEL: &str = "(standard input)";
+
+/// The maximum size of an input that is read into memory (such as a mailbox), without `max_filesize`.
+const MAX_READ_SIZE: u64 = 1 << 30;
+
 // enum
 #[derive(Clone, Copy)]
 pub enum ColorMode {
@@ -38,6 +86,122 @@ impl Default for ColorMode {
     }
 }
 
+/// What to print for each searched file.
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub enum OutputMode {
+    /// Print the matching lines.
+    #[default]
+    Lines,
+
+    /// Print only the paths of the files with a match. (`grep -l`)
+    FilesWithMatches,
+
+    /// Print only the paths of the files without a match. (`grep -L`)
+    FilesWithoutMatch,
+
+    /// Print the number of matching lines of each file. (`grep -c`)
+    Count,
+
+    /// Print nothing, and stop at the first match. (`grep -q`)
+    Quiet,
+
+    /// Print the matches as JSON Lines.
+    Json,
+
+    /// Print the matches as a single SARIF log.
+    Sarif,
+
+    /// Print nothing, and get the lowest error count of the approximate matches. (the first pass of `--best-match`)
+    BestErrors,
+}
+
+impl OutputMode {
+    /// Get the kind of the summary printer, if this mode prints a summary of each file.
+    fn summary_kind(self) -> Option<SummaryKind> {
+        match self {
+            OutputMode::Lines | OutputMode::Json | OutputMode::Sarif | OutputMode::BestErrors => None,
+            OutputMode::FilesWithMatches => Some(SummaryKind::PathWithMatch),
+            OutputMode::FilesWithoutMatch => Some(SummaryKind::PathWithoutMatch),
+            OutputMode::Count => Some(SummaryKind::Count),
+            OutputMode::Quiet => Some(SummaryKind::Quiet),
+        }
+    }
+}
+
+/// The order of the files in the output.
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub enum SortMode {
+    /// The order of the walk: the paths in the given order, and each directory by file name.
+    #[default]
+    Walk,
+
+    /// Sorted by path.
+    Path,
+
+    /// Sorted by the last modified time, oldest first.
+    Modified,
+
+    /// Printed as soon as each file is searched. The fastest, but the order changes on each run.
+    None,
+}
+
+/// The result of `scan`.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct ScanStatus {
+    /// Whether any line matched.
+    pub matched: bool,
+
+    /// Whether any path could not be searched.
+    pub error: bool,
+
+    /// With `OutputMode::BestErrors`, the lowest error count of the matches.
+    pub best_errors: Option<u32>,
+}
+
+impl ScanStatus {
+    /// Get the exit status of grep. 0: matched, 1: not matched, 2: error.
+    /// With `grep -q`, a match wins over an error.
+    pub fn exit_code(&self, is_quiet: bool) -> i32 {
+        match (self.matched, self.error) {
+            (true, _) if is_quiet => 0,
+            (_, true) => 2,
+            (true, false) => 0,
+            (false, false) => 1,
+        }
+    }
+}
+
+/// The printed output of a path.
+struct SearchOutput {
+    bytes: Vec<u8>,
+    matched: bool,
+    error: Option<io::Error>,
+
+    /// With `OutputMode::BestErrors`, the lowest error count of the matches.
+    best_errors: Option<u32>,
+}
+
+impl SearchOutput {
+    fn new() -> Self {
+        SearchOutput {
+            bytes: vec![],
+            matched: false,
+            error: None,
+            best_errors: None,
+        }
+    }
+
+    /// Append the output of a part of the path, such as a member of an archive. The first error is kept.
+    fn append(&mut self, other: SearchOutput) {
+        self.bytes.extend(other.bytes);
+        self.matched |= other.matched;
+        if self.error.is_none() {
+            self.error = other.error;
+        }
+        self.best_errors = min_errors(self.best_errors, other.best_errors);
+    }
+}
+
 #[derive(Default, Clone, Copy)]
 pub struct Config<'main> {
     /// The number of lines after a match to include.
@@ -48,8 +212,26 @@ pub struct Config<'main> {
     /// This is a searcher config item.
     before_context: usize,
 
+    /// Whether to use colors in the output.
+    /// This is a printer config item.
     color: ColorMode,
 
+    /// The colors of path, line number and match. If None, the default colors are used.
+    /// This is a printer config item.
+    colors: Option<&'main Colors>,
+
+    /// Finds the substituted characters of a match, to highlight them separately.
+    /// This is a printer config item.
+    explainer: Option<&'main Explainer>,
+
+    /// Whether to list the substituted characters of each match.
+    /// This is a printer config item.
+    explain: bool,
+
+    /// The printed path of the file being searched, whose archive members and document parts
+    /// are printed as `path!name`. This is a printer config item, set for each file.
+    container: Option<&'main Path>,
+
     /// The binary data detection strategy.
     /// This is a searcher config item.
     binary: bool,
@@ -88,6 +270,26 @@ pub struct Config<'main> {
     /// This is a searcher config item.
     line_number: bool,
 
+    /// The line printed between the context groups, instead of `--`.
+    /// This is a printer config item.
+    group_separator: Option<&'main [u8]>,
+
+    /// Whether to print nothing between the context groups.
+    /// This is a printer config item.
+    no_group_separator: bool,
+
+    /// Whether a context option is given. Like GNU grep, the groups are separated also with 0 context lines.
+    /// This is a printer config item.
+    with_context: bool,
+
+    /// The byte that ends a record, instead of a line break. Set by `--null-data` and `-d`.
+    /// This is a searcher config item.
+    line_terminator: Option<u8>,
+
+    /// The delimiter of records longer than a byte (e.g. `\n\n`, paragraphs), replaced by NUL before searching.
+    /// This is a searcher config item.
+    record_delimiter: Option<&'main [u8]>,
+
     /// The memory map strategy.
     /// This is a searcher config item.
     mmap: bool,
@@ -96,9 +298,69 @@ pub struct Config<'main> {
     /// This is a searcher config item.
     multi_line: bool,
 
+    /// Whether to decompress the compressed files (e.g. `*.gz`), and search the members of the archives, before searching.
+    /// This is a searcher config item.
+    search_zip: bool,
+
+    /// The maximum size and nesting depth of the archive members.
+    /// This is a searcher config item.
+    archive_limits: Limits,
+
+    /// Whether to extract the text of the office documents (docx, xlsx, pptx, odt, ods and odp).
+    /// This is a searcher config item.
+    documents: bool,
+
+    /// Whether to parse every file as mbox or a message, not only `*.mbox` and `*.eml`.
+    /// This is a searcher config item.
+    mail: bool,
+
+    /// Whether to print the file path of each match.
+    /// This is a printer config item.
+    pub with_filename: bool,
+
+    /// What to print for each searched file.
+    /// This is a printer config item.
+    pub output_mode: OutputMode,
+
+    /// The order of the files in the output.
+    /// This is a walker config item.
+    pub sort: SortMode,
+
     /// Set the maximum amount of matching lines that are printed.
     /// This is a printer config item.
     pub max_matches: Option<u64>,
+
+    /// Globs of the file names to search. If empty, all files are searched.
+    /// This is a walker config item.
+    include: &'main [String],
+
+    /// Globs of the file names to skip.
+    /// This is a walker config item.
+    exclude: &'main [String],
+
+    /// Globs of the directory names to skip.
+    /// This is a walker config item.
+    exclude_dir: &'main [String],
+
+    /// Whether to search hidden files and directories.
+    /// This is a walker config item.
+    hidden: bool,
+
+    /// Whether to ignore `.gitignore`, `.ignore` and the other ignore files.
+    /// This is a walker config item.
+    no_ignore: bool,
+
+    /// The maximum depth to descend into directories.
+    /// This is a walker config item.
+    max_depth: Option<usize>,
+
+    /// Whether to follow symbolic links.
+    /// This is a walker config item.
+    follow: bool,
+
+    /// Files larger than this size (in bytes) are skipped.
+    /// This is a walker config item.
+    max_filesize: Option<u64>,
 }
 
 impl<'main> Config<'main> {
@@ -135,6 +397,53 @@ impl<'main> Config<'main> {
         self
     }
 
+    pub fn colors(&mut self, colors: &'main Colors) -> &mut Self {
+        self.colors = Some(colors);
+        self
+    }
+
+    pub fn explainer(&mut self, explainer: &'main Explainer) -> &mut Self {
+        self.explainer = Some(explainer);
+        self
+    }
+
+    pub fn explain(&mut self, yes: bool) -> &mut Self {
+        self.explain = yes;
+        self
+    }
+
+    /// Whether the output is colored. With `ColorMode::Auto`, only if stdout is a terminal.
+    pub fn is_color(&self) -> bool {
+        match self.color {
+            ColorMode::None => false,
+            ColorMode::Always => true,
+            ColorMode::Auto => io::stdout().is_terminal() && env::var("TERM").map_or(true, |t| t != "dumb"),
+        }
+    }
+
+    /// Build the output buffer of the printers.
+    pub fn build_buffer(&self) -> Buffer {
+        if self.is_color() {
+            Buffer::ansi()
+        } else {
+            Buffer::no_color()
+        }
+    }
+
+    /// Get the colors of the printers.
+    pub fn get_colors(&self) -> Colors {
+        match self.colors {
+            Some(colors) => colors.clone(),
+            None => Colors::default(),
+        }
+    }
+
+    /// Whether the matches need `AnnotatedPrinter`, to highlight the substituted characters
+    /// when the output is colored, or to list them with `--explain`.
+    fn is_explained(&self) -> bool {
+        self.explainer.is_some() && (self.explain || self.is_color())
+    }
+
     pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
         self.case_insensitive = yes;
         self
@@ -165,11 +474,58 @@ impl<'main> Config<'main> {
         self
     }
 
+    pub fn group_separator(&mut self, separator: &'main [u8]) -> &mut Self {
+        self.group_separator = Some(separator);
+        self
+    }
+
+    pub fn no_group_separator(&mut self, yes: bool) -> &mut Self {
+        self.no_group_separator = yes;
+        self
+    }
+
+    /// The line printed between the context groups. None prints nothing.
+    pub fn with_context(&mut self, yes: bool) -> &mut Self {
+        self.with_context = yes;
+        self
+    }
+
+    /// Whether the matching lines are separated into groups by the printer, not by the searcher,
+    /// which breaks them only with one or more context lines.
+    fn is_group_break(&self) -> bool {
+        self.with_context && self.before_context == 0 && self.after_context == 0 && !self.passthru
+    }
+
+    fn context_separator(&self) -> Option<&'main [u8]> {
+        match self.group_separator {
+            _ if self.no_group_separator => None,
+            Some(separator) => Some(separator),
+            None => Some(b"--"),
+        }
+    }
+
     pub fn line_number(&mut self, yes: bool) -> &mut Self {
         self.line_number = yes;
         self
     }
 
+    /// Search NUL-terminated records, instead of lines.
+    pub fn null_data(&mut self, yes: bool) -> &mut Self {
+        self.line_terminator = if yes { Some(b'\0') } else { None };
+        self
+    }
+
+    /// Search records separated by `delimiter`, instead of lines.
+    pub fn delimiter(&mut self, delimiter: &'main [u8]) -> &mut Self {
+        if delimiter.len() == 1 {
+            self.line_terminator = Some(delimiter[0]);
+        } else {
+            self.line_terminator = Some(b'\0');
+            self.record_delimiter = Some(delimiter);
+        }
+        self
+    }
+
     pub fn mmap(&mut self, yes: bool) -> &mut Self {
         self.mmap = yes;
         self
@@ -185,6 +541,86 @@ impl<'main> Config<'main> {
         self
     }
 
+    pub fn search_zip(&mut self, yes: bool) -> &mut Self {
+        self.search_zip = yes;
+        self
+    }
+
+    pub fn max_member_size(&mut self, size: u64) -> &mut Self {
+        self.archive_limits.max_member_size = Some(size);
+        self
+    }
+
+    pub fn max_archive_depth(&mut self, depth: usize) -> &mut Self {
+        self.archive_limits.max_depth = depth;
+        self
+    }
+
+    pub fn documents(&mut self, yes: bool) -> &mut Self {
+        self.documents = yes;
+        self
+    }
+
+    pub fn mail(&mut self, yes: bool) -> &mut Self {
+        self.mail = yes;
+        self
+    }
+
+    pub fn with_filename(&mut self, yes: bool) -> &mut Self {
+        self.with_filename = yes;
+        self
+    }
+
+    pub fn output_mode(&mut self, mode: OutputMode) -> &mut Self {
+        self.output_mode = mode;
+        self
+    }
+
+    pub fn sort(&mut self, mode: SortMode) -> &mut Self {
+        self.sort = mode;
+        self
+    }
+
+    pub fn include(&mut self, globs: &'main [String]) -> &mut Self {
+        self.include = globs;
+        self
+    }
+
+    pub fn exclude(&mut self, globs: &'main [String]) -> &mut Self {
+        self.exclude = globs;
+        self
+    }
+
+    pub fn exclude_dir(&mut self, globs: &'main [String]) -> &mut Self {
+        self.exclude_dir = globs;
+        self
+    }
+
+    pub fn hidden(&mut self, yes: bool) -> &mut Self {
+        self.hidden = yes;
+        self
+    }
+
+    pub fn no_ignore(&mut self, yes: bool) -> &mut Self {
+        self.no_ignore = yes;
+        self
+    }
+
+    pub fn max_depth(&mut self, num: usize) -> &mut Self {
+        self.max_depth = Some(num);
+        self
+    }
+
+    pub fn follow(&mut self, yes: bool) -> &mut Self {
+        self.follow = yes;
+        self
+    }
+
+    pub fn max_filesize(&mut self, size: u64) -> &mut Self {
+        self.max_filesize = Some(size);
+        self
+    }
+
     pub fn build_matcher(&mut self, pattern: &str) -> Result<RegexMatcher, Box<Error>> {
         Ok(RegexMatcherBuilder::new()
             .case_insensitive(self.case_insensitive)
@@ -192,68 +628,872 @@ impl<'main> Config<'main> {
             .build(&pattern)?)
     }
 
+    /// Whether `error` from `build_matcher` is because the compiled regex is too large.
+    /// The other errors are in the pattern itself, so another engine would not help.
+    pub fn is_size_limit_error(error: &(dyn Error + 'static)) -> bool {
+        match error.downcast_ref::<grep_regex::Error>().map(|e| e.kind()) {
+            Some(grep_regex::ErrorKind::Regex(message)) => message.starts_with("compiled regex exceeds size limit"),
+            _ => false,
+        }
+    }
+
     pub fn build_searcher(&mut self) -> Searcher {
         let mut search_builder = SearcherBuilder::new();
 
         search_builder
             .after_context(self.after_context)
             .before_context(self.before_context)
-            .line_terminator(LineTerminator::byte(LINE_ENDING))
+            .line_terminator(LineTerminator::byte(self.line_terminator.unwrap_or(LINE_ENDING)))
             .line_number(self.line_number)
-            .multi_line(self.multi_line);
+            .invert_match(self.invert_match)
+            .passthru(self.passthru)
+            .multi_line(self.multi_line)
+            .bom_sniffing(self.bom_sniffing);
+
+        // an unknown label is reported by `build_encoding` before searching.
+        if let Ok(encoding) = self.build_encoding() {
+            search_builder.encoding(encoding);
+        }
 
-        if self.binary {
+        // NUL is the record terminator of `--null-data`, not the sign of binary data.
+        if self.binary && self.line_terminator != Some(b'\0') {
             search_builder.binary_detection(BinaryDetection::quit(0));
         }
 
         search_builder.build()
     }
 
+    /// Build the encoding of `encoding`, a label of the Encoding Standard (e.g. `sjis`, `euc-jp`, `utf-16le`).
+    pub fn build_encoding(&self) -> Result<Option<Encoding>, Box<dyn Error>> {
+        match self.encoding {
+            Some(label) => Ok(Some(Encoding::new(label)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Build the file name filter of `include`, `exclude` and `exclude_dir`.
+    /// The globs match like `.gitignore`, so a glob without `/` matches the base name.
+    pub fn build_overrides(&self) -> Result<Override, Box<dyn Error>> {
+        let mut builder = OverrideBuilder::new(".");
+
+        // later globs take priority, so the excludes must be added after the includes.
+        for glob in self.include {
+            builder.add(glob)?;
+        }
+        for glob in self.exclude {
+            builder.add(&format!("!{}", glob))?;
+        }
+        for glob in self.exclude_dir {
+            builder.add(&format!("!{}/", glob.trim_end_matches('/')))?;
+        }
+
+        Ok(builder.build()?)
+    }
+
     pub fn build_walker(&mut self, path_list: Vec<PathBuf>) -> Result<WalkParallel, Box<Error>> {
+        Ok(self.build_walk_builder(path_list)?.build_parallel())
+    }
+
+    /// Build a single-threaded walker, that walks each directory in order of file name.
+    pub fn build_sorted_walker(&mut self, path_list: Vec<PathBuf>) -> Result<Walk, Box<dyn Error>> {
+        let mut builder = self.build_walk_builder(path_list)?;
+        builder.sort_by_file_name(|a, b| a.cmp(b));
+
+        Ok(builder.build())
+    }
+
+    fn build_walk_builder(&mut self, path_list: Vec<PathBuf>) -> Result<WalkBuilder, Box<dyn Error>> {
         let mut paths = path_list.iter();
-        let mut builder = WalkBuilder::new(paths.next().unwrap());
-        builder.ignore(true).threads(cmp::min(12, num_cpus::get()));
+        let first = paths.next().ok_or("no path to walk")?;
+
+        let mut builder = WalkBuilder::new(first);
+        builder
+            .hidden(!self.hidden)
+            .ignore(!self.no_ignore)
+            .git_ignore(!self.no_ignore)
+            .git_global(!self.no_ignore)
+            .git_exclude(!self.no_ignore)
+            .parents(!self.no_ignore)
+            .max_depth(self.max_depth)
+            .follow_links(self.follow)
+            .max_filesize(self.max_filesize)
+            .threads(cmp::min(12, num_cpus::get()));
+
+        // not `WalkBuilder::overrides`, because its whitelist also includes the files of ignore files.
+        let overrides = self.build_overrides()?;
+        builder.filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            !overrides.matched(entry.path(), is_dir).is_ignore()
+        });
 
-        // paths.for_each(|p| {
-        //     builder.add(p);
-        // });
+        paths.for_each(|p| {
+            builder.add(p);
+        });
 
-        Ok(builder.build_parallel())
+        Ok(builder)
     }
 }
 
-pub fn scan(config: Config, pattern: &str, path_list: Vec<PathBuf>) {
-    let mut config = config.clone();
+/// The input of a search.
+#[derive(Clone, Copy)]
+enum Input<'a> {
+    /// A file. The path `-` is stdin.
+    Path(&'a Path),
 
-    let matcher = config.build_matcher(pattern).unwrap();
-    let walker = config.build_walker(path_list);
+    /// A member of an archive.
+    Bytes(&'a [u8]),
+}
+
+/// Search `input` with `sink`.
+/// With `search_zip`, a compressed file is searched through its decoder.
+fn search_input<S: Sink>(
+    config: &Config,
+    searcher: &mut Searcher,
+    matcher: &HmgMatcher,
+    input: Input,
+    sink: S,
+) -> Result<(), S::Error> {
+    let path = match input {
+        Input::Path(path) => path,
+        Input::Bytes(bytes) => return searcher.search_slice(matcher, bytes, sink),
+    };
+
+    if path == Path::new(STDIN_PATH) {
+        let stdin = io::stdin();
+        return searcher.search_reader(matcher, stdin.lock(), sink);
+    }
+
+    if config.search_zip {
+        if let Some(reader) = decompress::open(path, config.max_filesize).map_err(S::Error::error_io)? {
+            return searcher.search_reader(matcher, reader, sink);
+        }
+    }
+
+    searcher.search_path(matcher, path, sink)
+}
+
+/// Search `path` and get the printed output.
+/// The text of an office document is extracted before searching.
+/// The messages of a mail file are printed as `mail.mbox!<message-id>!Subject`.
+/// With `search_zip`, each member of an archive is printed as `archive.zip!path/in/archive`.
+fn search_output(config: &Config, searcher: &mut Searcher, matcher: &HmgMatcher, path: &Path) -> SearchOutput {
+    let mut config = *config;
+    config.container = Some(if path == Path::new(STDIN_PATH) { Path::new(STDIN_LABEL) } else { path });
+    let config = &config;
+
+    if let HmgMatcher::CsvColumn(m) = matcher {
+        return search_csv(config, m, path);
+    }
+
+    if let Some(delimiter) = config.record_delimiter {
+        return match read_input(config, path) {
+            Ok((data, display_path)) => {
+                let data = replace_delimiter(&data, delimiter);
+                print_input(config, searcher, matcher, Input::Bytes(&data), display_path)
+            }
+            Err(e) => SearchOutput {
+                error: Some(e),
+                ..SearchOutput::new()
+            },
+        };
+    }
+
+    if config.mail {
+        return match read_input(config, path) {
+            Ok((data, display_path)) => search_mail(config, searcher, matcher, display_path, &data),
+            Err(e) => SearchOutput {
+                error: Some(e),
+                ..SearchOutput::new()
+            },
+        };
+    }
+
+    if path == Path::new(STDIN_PATH) {
+        return print_input(config, searcher, matcher, Input::Path(path), Path::new(STDIN_LABEL));
+    }
+
+    if let Some(kind) = DocumentKind::from_path(path).filter(|_| config.documents) {
+        return match File::open(path) {
+            Ok(file) => search_document(config, searcher, matcher, path, kind, file),
+            Err(e) => SearchOutput {
+                error: Some(e),
+                ..SearchOutput::new()
+            },
+        };
+    }
+
+    if config.search_zip {
+        if let Some(kind) = ArchiveKind::from_path(path) {
+            return search_archive(config, searcher, matcher, path, kind);
+        }
+    }
+
+    print_input(config, searcher, matcher, Input::Path(path), path)
+}
+
+/// Search the members of the archive at `path`, and get the printed output of all of them.
+fn search_archive(
+    config: &Config,
+    searcher: &mut Searcher,
+    matcher: &HmgMatcher,
+    path: &Path,
+    kind: ArchiveKind,
+) -> SearchOutput {
+    // the members are told apart only by their names.
+    let mut member_config = *config;
+    member_config.with_filename(true);
+
+    let mut output = SearchOutput::new();
+
+    let result = archive::read_members(path, kind, &config.archive_limits, &mut |member, data| {
+        let member_output = if let Some(kind) = DocumentKind::from_path(member).filter(|_| config.documents) {
+            search_document(&member_config, searcher, matcher, member, kind, Cursor::new(data))
+        } else if config.mail && MailKind::from_path(member).is_some() {
+            search_mail(&member_config, searcher, matcher, member, data)
+        } else if is_binary_member(config, data) {
+            return true;
+        } else {
+            print_input(&member_config, searcher, matcher, Input::Bytes(data), member)
+        };
+        output.append(member_output);
+
+        // with `grep -q`, the first match is enough.
+        !(output.matched && config.output_mode == OutputMode::Quiet)
+    });
+    if let Err(e) = result {
+        output.error = Some(e);
+    }
+
+    output
+}
+
+/// Whether `data`, a member of an archive, is binary, such as an image or an executable.
+/// Like a file of GNU grep, a member with NUL is binary, unless it is UTF-16 with a BOM,
+/// or `--encoding` or `--null-data` is given.
+fn is_binary_member(config: &Config, data: &[u8]) -> bool {
+    let is_utf16 = config.bom_sniffing && (data.starts_with(b"\xFF\xFE") || data.starts_with(b"\xFE\xFF"));
+
+    config.encoding.is_none() && config.line_terminator != Some(b'\0') && !is_utf16 && data.contains(&0)
+}
+
+/// Search the CSV at `path` with the columns resolved by its header.
+/// Each record is searched as a record of `--null-data`, so a line number is a row number,
+/// and the record is printed as it is, also with the line breaks in a quoted cell.
+fn search_csv(config: &Config, matcher: &CsvColumnMatcher, path: &Path) -> SearchOutput {
+    let failed = |e: io::Error| SearchOutput {
+        error: Some(e),
+        ..SearchOutput::new()
+    };
+
+    let (data, display_path) = match read_input(config, path) {
+        Ok((data, display_path)) => (decode_input(config, data), display_path),
+        Err(e) => return failed(e),
+    };
+
+    let delimiter = matcher.delimiter_of(path);
+    let records = match csvcolumn::records(&data, delimiter) {
+        Ok(result) => result,
+        Err(e) => return failed(io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+    };
+    let file_matcher = match matcher.for_file(delimiter, &records.header) {
+        Ok(m) => HmgMatcher::CsvColumn(m),
+        Err(e) => return failed(io::Error::new(io::ErrorKind::InvalidInput, e.to_string())),
+    };
+
+    let mut text = Vec::with_capacity(data.len() + records.ranges.len());
+    for (start, end) in records.ranges {
+        text.extend_from_slice(&data[start..end]);
+        text.push(csvcolumn::RECORD_TERMINATOR);
+    }
+
+    // the input is already decoded.
+    let mut record_config = *config;
+    record_config.null_data(true);
+    record_config.encoding = None;
+    let mut searcher = record_config.build_searcher();
+
+    print_input(&record_config, &mut searcher, &file_matcher, Input::Bytes(&text), display_path)
+}
 
-    // let queue: Arc<ArrayQueue<Option<String>>> = Arc::new(ArrayQueue::new(10));
+/// Transcode `data`, an input that is parsed before searching, to UTF-8 like the searcher does,
+/// by `encoding` or the BOM. Without both, `data` is returned as it is.
+fn decode_input(config: &Config, data: Vec<u8>) -> Vec<u8> {
+    let encoding = match config.encoding {
+        Some(label) => encoding_rs::Encoding::for_label(label.as_bytes()),
+        None if config.bom_sniffing => encoding_rs::Encoding::for_bom(&data).map(|(e, _)| e),
+        None => None,
+    };
+
+    match encoding {
+        // `decode` also removes the BOM.
+        Some(encoding) => encoding.decode(&data).0.into_owned().into_bytes(),
+        None => data,
+    }
+}
+
+/// Replace each `delimiter` in `data` with NUL, the terminator of the records.
+fn replace_delimiter(data: &[u8], delimiter: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len());
+
+    let mut i = 0;
+    while i < data.len() {
+        if data[i..].starts_with(delimiter) {
+            result.push(b'\0');
+            i += delimiter.len();
+        } else {
+            result.push(data[i]);
+            i += 1;
+        }
+    }
+
+    result
+}
+
+/// Read the whole input at `path`, and get it with the path to print. `-` is stdin.
+fn read_input<'a>(config: &Config, path: &'a Path) -> io::Result<(Vec<u8>, &'a Path)> {
+    if path == Path::new(STDIN_PATH) {
+        let data = read_limited(config, io::stdin().lock())?;
+        return Ok((data, Path::new(STDIN_LABEL)));
+    }
+
+    Ok((read_file(config, path)?, path))
+}
+
+/// Read the whole file at `path`. With `search_zip`, a compressed file is decompressed.
+fn read_file(config: &Config, path: &Path) -> io::Result<Vec<u8>> {
+    if config.search_zip {
+        if let Some(reader) = decompress::open(path, config.max_filesize)? {
+            return read_limited(config, reader);
+        }
+    }
+
+    read_limited(config, File::open(path)?)
+}
+
+/// Read all of `rdr` into memory, failing over `max_filesize` bytes, or `MAX_READ_SIZE` without it.
+fn read_limited<R: Read>(config: &Config, rdr: R) -> io::Result<Vec<u8>> {
+    let limit = config.max_filesize.unwrap_or(MAX_READ_SIZE);
+
+    let mut data = vec![];
+    rdr.take(limit + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > limit {
+        let message = format!("input exceeds the limit of {} bytes", limit);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+
+    Ok(data)
+}
+
+/// Search the text of the document in `rdr`, and get the printed output of all parts.
+/// A sheet or a slide is printed as `book.xlsx!Sheet1`.
+fn search_document<R: Read + Seek>(
+    config: &Config,
+    searcher: &mut Searcher,
+    matcher: &HmgMatcher,
+    path: &Path,
+    kind: DocumentKind,
+    rdr: R,
+) -> SearchOutput {
+    match document::extract(kind, rdr, config.archive_limits.max_member_size) {
+        Ok(parts) => search_parts(config, searcher, matcher, path, parts),
+        Err(e) => SearchOutput {
+            error: Some(e),
+            ..SearchOutput::new()
+        },
+    }
+}
+
+/// Search the headers and the bodies of the messages in `data`, the mail file at `path`.
+/// A part is printed as `mail.mbox!<message-id>!Subject`, or `mail.mbox!#2!body` without the Message-ID.
+fn search_mail(config: &Config, searcher: &mut Searcher, matcher: &HmgMatcher, path: &Path, data: &[u8]) -> SearchOutput {
+    let kind = MailKind::from_path(path).unwrap_or_else(|| MailKind::sniff(data));
+    let mails = match mail::extract(kind, data) {
+        Ok(mails) => mails,
+        Err(e) => {
+            return SearchOutput {
+                error: Some(e),
+                ..SearchOutput::new()
+            }
+        }
+    };
+
+    let mut parts = vec![];
+    for (i, mail) in mails.into_iter().enumerate() {
+        let id = match mail.id {
+            Some(id) => format!("<{}>", id),
+            None => format!("#{}", i + 1),
+        };
+        for part in mail.parts {
+            parts.push(document::Part {
+                name: Some(format!("{}!{}", id, part.name.unwrap_or_default())),
+                text: part.text,
+            });
+        }
+    }
+
+    search_parts(config, searcher, matcher, path, parts)
+}
+
+/// Search each part of a file, and get the printed output of all parts.
+/// The summary of `-l`, `-L`, `-c` and `-q` is of the whole file, so the parts are searched together.
+fn search_parts(
+    config: &Config,
+    searcher: &mut Searcher,
+    matcher: &HmgMatcher,
+    path: &Path,
+    parts: Vec<document::Part>,
+) -> SearchOutput {
+    if config.output_mode.summary_kind().is_some() {
+        // each part is one or more lines, also if it does not end with a line terminator.
+        let terminator = config.line_terminator.unwrap_or(b'\n');
+        let mut text = vec![];
+        for part in parts.iter().filter(|p| !p.text.is_empty()) {
+            text.extend_from_slice(part.text.as_bytes());
+            if !text.ends_with(&[terminator]) {
+                text.push(terminator);
+            }
+        }
+
+        return print_input(config, searcher, matcher, Input::Bytes(&text), path);
+    }
+
+    let mut output = SearchOutput::new();
+    for part in parts {
+        let part_output = match part.name {
+            Some(name) => {
+                // the parts are told apart only by their names.
+                let mut part_config = *config;
+                part_config.with_filename(true);
+
+                let part_path = PathBuf::from(format!("{}!{}", path.to_string_lossy(), name));
+                print_input(&part_config, searcher, matcher, Input::Bytes(part.text.as_bytes()), &part_path)
+            }
+            None => print_input(config, searcher, matcher, Input::Bytes(part.text.as_bytes()), path),
+        };
+        output.append(part_output);
+
+        // with `grep -q`, the first match is enough.
+        if output.matched && config.output_mode == OutputMode::Quiet {
+            break;
+        }
+    }
+
+    output
+}
+
+/// Get the name of the archive member or the document part printed as `display_path`,
+/// which is `container!name`.
+fn part_name<'a>(container: &Path, display_path: &'a Path) -> Option<&'a str> {
+    let prefix = format!("{}!", container.to_string_lossy());
+    display_path.to_str()?.strip_prefix(prefix.as_str())
+}
+
+/// Search `input` with `sink`, the printer of the matching lines.
+/// With `-C0`, the matching lines that are not adjacent are separated like the context groups.
+fn search_lines<S: Sink>(
+    config: &Config,
+    searcher: &mut Searcher,
+    matcher: &HmgMatcher,
+    input: Input,
+    sink: S,
+) -> Result<(), S::Error> {
+    if config.is_group_break() {
+        search_input(config, searcher, matcher, input, GroupBreakSink::new(sink))
+    } else {
+        search_input(config, searcher, matcher, input, sink)
+    }
+}
+
+/// Search `input` and get the output printed with `display_path`.
+fn print_input(
+    config: &Config,
+    searcher: &mut Searcher,
+    matcher: &HmgMatcher,
+    input: Input,
+    display_path: &Path,
+) -> SearchOutput {
+    if config.output_mode == OutputMode::BestErrors {
+        let mut sink = BestErrorsSink::new(matcher);
+        let error = search_input(config, searcher, matcher, input, &mut sink).err();
+
+        return SearchOutput {
+            bytes: vec![],
+            matched: sink.has_matched(),
+            error,
+            best_errors: sink.best_errors(),
+        };
+    }
+
+    if config.output_mode == OutputMode::Json {
+        let mut printer = JsonPrinter::new(matcher);
+        printer.path(display_path).max_matches(config.max_matches);
+        if let Some(explainer) = config.explainer {
+            printer.explainer(explainer);
+        }
+
+        let error = search_input(config, searcher, matcher, input, &mut printer).err();
+        let matched = printer.has_matched();
+
+        return SearchOutput {
+            bytes: printer.into_inner(),
+            matched,
+            error,
+            best_errors: None,
+        };
+    }
+
+    if config.output_mode == OutputMode::Sarif {
+        // the URI is of the file, and the member or the part is a logical location in it.
+        let container = config.container.unwrap_or(display_path);
+        let mut printer = SarifPrinter::new(matcher, container);
+        printer.max_matches(config.max_matches);
+        if let Some(name) = part_name(container, display_path) {
+            printer.part(name);
+        }
+        if let Some(explainer) = config.explainer {
+            printer.explainer(explainer);
+        }
+
+        let error = search_input(config, searcher, matcher, input, &mut printer).err();
+        let matched = printer.has_matched();
+
+        return SearchOutput {
+            bytes: printer.into_inner(),
+            matched,
+            error,
+            best_errors: None,
+        };
+    }
+
+    if let Some(kind) = config.output_mode.summary_kind() {
+        // the summary does not need the annotations, so any matcher can use it.
+        let mut printer = SummaryBuilder::new()
+            .kind(kind)
+            .path(config.with_filename || kind != SummaryKind::Count)
+            .max_matches(config.max_matches)
+            .exclude_zero(false)
+            .color_specs(config.get_colors().specs)
+            .build(config.build_buffer());
+
+        let mut sink = printer.sink_with_path(matcher, display_path);
+        let error = search_input(config, searcher, matcher, input, &mut sink).err();
+        let matched = sink.has_match();
+
+        return SearchOutput {
+            bytes: printer.into_inner().into_inner(),
+            matched,
+            error,
+            best_errors: None,
+        };
+    }
+
+    // the records of `--null-data` and `-d` are printed as lines by `AnnotatedPrinter`.
+    if matcher.is_annotated() || config.is_explained() || config.line_terminator.is_some() {
+        let mut printer = AnnotatedPrinter::new(matcher, config.only_matching, config.build_buffer());
+        printer
+            .max_matches(config.max_matches)
+            .colors(config.get_colors())
+            .separator(config.context_separator());
+        if let Some(explainer) = config.explainer.filter(|_| config.is_explained()) {
+            printer.explainer(explainer, config.explain);
+        }
+        if config.with_filename {
+            printer.path(display_path);
+        }
+
+        let error = search_lines(config, searcher, matcher, input, &mut printer).err();
+        let matched = printer.has_matched();
+
+        SearchOutput {
+            bytes: printer.into_inner(),
+            matched,
+            error,
+            best_errors: None,
+        }
+    } else {
+        let mut printer = StandardBuilder::new()
+            .only_matching(config.only_matching)
+            .max_matches(config.max_matches)
+            .color_specs(config.get_colors().specs)
+            .separator_context(config.context_separator().map(|s| s.to_vec()))
+            .build(config.build_buffer());
+
+        let (error, matched) = if config.with_filename {
+            let mut sink = printer.sink_with_path(matcher, display_path);
+            (search_lines(config, searcher, matcher, input, &mut sink).err(), sink.has_match())
+        } else {
+            let mut sink = printer.sink(matcher);
+            (search_lines(config, searcher, matcher, input, &mut sink).err(), sink.has_match())
+        };
+
+        SearchOutput {
+            bytes: printer.into_inner().into_inner(),
+            matched,
+            error,
+            best_errors: None,
+        }
+    }
+}
+
+/// The shared state of the searcher threads of `scan`.
+#[derive(Default)]
+struct ScanState {
+    matched: AtomicBool,
+    error: AtomicBool,
+
+    /// With `OutputMode::Sarif`, the results of all files, to print them in a single log.
+    sarif_results: Mutex<Vec<u8>>,
+
+    /// With `OutputMode::BestErrors`, the lowest error count of all files.
+    best_errors: Mutex<Option<u32>>,
+}
+
+impl ScanState {
+    fn status(&self) -> ScanStatus {
+        ScanStatus {
+            matched: self.matched.load(Ordering::SeqCst),
+            error: self.error.load(Ordering::SeqCst),
+            best_errors: *self.best_errors.lock().unwrap(),
+        }
+    }
+}
+
+/// Print `output` of `path`, and record whether it matched or failed.
+fn print_output(config: &Config, output: SearchOutput, path: &Path, state: &ScanState) {
+    if config.output_mode == OutputMode::Sarif {
+        state.sarif_results.lock().unwrap().extend_from_slice(&output.bytes);
+    } else {
+        // the bytes are written as is, the input may not be UTF-8. a closed pipe (such as `| head`) is not an error.
+        if let Err(e) = io::stdout().lock().write_all(&output.bytes) {
+            if e.kind() != io::ErrorKind::BrokenPipe {
+                eprintln!("hmgrep: {}", e);
+                state.error.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+
+    if output.matched {
+        state.matched.store(true, Ordering::SeqCst);
+    }
+    if let Some(e) = output.error {
+        report_error(config, state, &format!("{}: {}", path.display(), e));
+    }
+    if output.best_errors.is_some() {
+        let mut best_errors = state.best_errors.lock().unwrap();
+        *best_errors = min_errors(*best_errors, output.best_errors);
+    }
+}
+
+/// Print the error `message`, and record that a path could not be searched.
+/// The first pass of `--best-match` does not print it, the second pass does.
+fn report_error(config: &Config, state: &ScanState, message: &str) {
+    if config.output_mode != OutputMode::BestErrors {
+        eprintln!("hmgrep: {}", message);
+    }
+    state.error.store(true, Ordering::SeqCst);
+}
+
+/// Get the lower of two error counts.
+fn min_errors(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        _ => a.or(b),
+    }
+}
+
+/// Get the files to search in `path_list`, in walk order.
+/// With `SortMode::Path` or `SortMode::Modified`, all files are walked first to sort them.
+/// Each file is sent with its index in the output, after taking a slot of `window`.
+fn walk_files(
+    config: &mut Config,
+    path_list: Vec<PathBuf>,
+    state: &ScanState,
+    files: channel::Sender<(usize, PathBuf)>,
+    window: channel::Sender<()>,
+) {
+    let mut walked = vec![];
+    let mut count = 0;
+
+    for entry in config.build_sorted_walker(path_list).unwrap() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                report_error(config, state, &e.to_string());
+                continue;
+            }
+        };
+
+        // directories are walked, not searched.
+        if entry.path().is_dir() {
+            continue;
+        }
+
+        if config.sort == SortMode::Walk {
+            // the searchers have stopped, such as by `-q`.
+            if window.send(()).is_err() || files.send((count, entry.into_path())).is_err() {
+                return;
+            }
+            count += 1;
+        } else {
+            walked.push(entry);
+        }
+    }
+
+    match config.sort {
+        SortMode::Path => walked.sort_by(|a, b| a.path().cmp(b.path())),
+        SortMode::Modified => walked.sort_by_key(|e| e.metadata().ok().and_then(|m| m.modified().ok())),
+        _ => {}
+    }
+
+    for (i, entry) in walked.into_iter().enumerate() {
+        if window.send(()).is_err() || files.send((i, entry.into_path())).is_err() {
+            return;
+        }
+    }
+}
+
+/// Search the files of `path_list` in parallel, and print them in order of `Config::sort`.
+/// The outputs are buffered, and printed in order by this thread.
+/// A file waiting for a slow file before it is printed holds a slot of the window, so at most
+/// `threads * 16` outputs are buffered, and the searchers wait until the slow file is printed.
+fn scan_ordered(config: &mut Config, matcher: &HmgMatcher, path_list: Vec<PathBuf>, state: &ScanState) {
+    let is_quiet = config.output_mode == OutputMode::Quiet;
+    let threads = cmp::min(12, num_cpus::get());
+
+    let (path_tx, path_rx) = channel::bounded::<(usize, PathBuf)>(threads * 4);
+    let (output_tx, output_rx) = channel::unbounded::<(usize, PathBuf, SearchOutput)>();
+    let (window_tx, window_rx) = channel::bounded::<()>(threads * 16);
+
+    thread::scope(|s| {
+        let mut walk_config = *config;
+        s.spawn(move || walk_files(&mut walk_config, path_list, state, path_tx, window_tx));
+
+        for _ in 0..threads {
+            let mut config = *config;
+            let matcher = matcher.clone();
+            let path_rx = path_rx.clone();
+            let output_tx = output_tx.clone();
+
+            s.spawn(move || {
+                let mut searcher = config.build_searcher();
+                for (i, path) in path_rx {
+                    // with `-q`, the first match decides the result.
+                    if is_quiet && state.matched.load(Ordering::SeqCst) {
+                        return;
+                    }
+
+                    let output = search_output(&config, &mut searcher, &matcher, &path);
+                    if output_tx.send((i, path, output)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(path_rx);
+        drop(output_tx);
+
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        for (i, path, output) in output_rx {
+            pending.insert(i, (path, output));
+
+            while let Some((path, output)) = pending.remove(&next) {
+                print_output(config, output, &path, state);
+                next += 1;
+                let _ = window_rx.recv();
+            }
+        }
+
+        // the searchers have stopped, such as by `-q`, so stop the walker waiting for a slot.
+        drop(window_rx);
+    });
+}
+
+/// Search the files of `path_list` in parallel, and print each as soon as it is searched.
+fn scan_unordered(config: &mut Config, matcher: &HmgMatcher, path_list: Vec<PathBuf>, state: &Arc<ScanState>) {
+    let is_quiet = config.output_mode == OutputMode::Quiet;
+    let walker = config.build_walker(path_list);
 
     walker.unwrap().run(|| {
-        // let push_queue = queue.clone();
+        let mut config = *config;
         let matcher = matcher.clone();
+        let state = state.clone();
         let mut searcher = config.build_searcher();
 
-        return Box::new(move |entry| {
-            if let Ok(entry) = entry {
-                let mut printer = StandardBuilder::new()
-                    .only_matching(config.only_matching)
-                    .max_matches(config.max_matches)
-                    .color_specs(ColorSpecs::default_with_color())
-                    .build(Ansi::new(vec![]));
+        Box::new(move |entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    report_error(&config, &state, &e.to_string());
+                    return ignore::WalkState::Continue;
+                }
+            };
+
+            // directories are walked, not searched.
+            if entry.path().is_dir() {
+                return ignore::WalkState::Continue;
+            }
 
-                let _ = searcher.search_path(&matcher, entry.path(), printer.sink(&matcher));
-                let output = String::from_utf8(printer.into_inner().into_inner()).unwrap();
+            let output = search_output(&config, &mut searcher, &matcher, entry.path());
+            print_output(&config, output, entry.path(), &state);
 
-                // push_queue.push(Some(output));
-                print!("{}", output);
+            // with `-q`, the first match decides the result.
+            if is_quiet && state.matched.load(Ordering::SeqCst) {
+                return ignore::WalkState::Quit;
             }
             ignore::WalkState::Continue
-        });
+        })
     });
 }
 
+/// Search every path of `path_list` and print the matches.
+/// The path `-` is stdin, which is searched without walking the filesystem.
+pub fn scan(config: Config, matcher: HmgMatcher, path_list: Vec<PathBuf>) -> ScanStatus {
+    let mut config = config.clone();
+    let is_quiet = config.output_mode == OutputMode::Quiet;
+
+    let state = Arc::new(ScanState::default());
+
+    let (stdin_list, path_list): (Vec<PathBuf>, Vec<PathBuf>) = path_list
+        .into_iter()
+        .partition(|p| p.as_path() == Path::new(STDIN_PATH));
+
+    // like GNU grep, `--include`, `--exclude` and `--exclude-dir` also skip the paths given on the
+    // command line. the walker does not filter them, because it only filters the entries under them.
+    let path_list: Vec<PathBuf> = match config.build_overrides() {
+        Ok(overrides) => path_list
+            .into_iter()
+            .filter(|p| !overrides.matched(p, p.is_dir()).is_ignore())
+            .collect(),
+        Err(_) => path_list,
+    };
+
+    if !stdin_list.is_empty() {
+        let mut searcher = config.build_searcher();
+        let output = search_output(&config, &mut searcher, &matcher, Path::new(STDIN_PATH));
+        print_output(&config, output, Path::new(STDIN_LABEL), &state);
+    }
+
+    // with `-q`, a match in stdin decides the result.
+    let is_decided = is_quiet && state.matched.load(Ordering::SeqCst);
+
+    if !path_list.is_empty() && !is_decided {
+        if config.sort == SortMode::None {
+            scan_unordered(&mut config, &matcher, path_list, &state);
+        } else {
+            scan_ordered(&mut config, &matcher, path_list, &state);
+        }
+    }
+
+    if config.output_mode == OutputMode::Sarif {
+        let results = state.sarif_results.lock().unwrap();
+        let rules = config.explainer.map_or(&[][..], |e| e.patterns());
+        print!("{}", sarif::build_log(&results, rules));
+    }
+
+    state.status()
+}
+
 // pub fn scan_path(pattern: &str, path: &str) -> Result<(Vec<String>), Box<Error>> {
 //     let matcher = RegexMatcher::new(&pattern)?;
 //     let parallel_walker = WalkBuilder::new(path)
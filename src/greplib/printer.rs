@@ -0,0 +1,404 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use grep_searcher::{Searcher, Sink, SinkContext, SinkContextKind, SinkFinish, SinkMatch};
+use termcolor::{Buffer, Color, ColorSpec, WriteColor};
+
+use super::color::Colors;
+use super::common::{match_lines, strip_terminator};
+use super::explain::Explainer;
+use super::matcher::{HmgMatch, HmgMatcher};
+
+/// Printer for the matches that `grep_printer::Standard` can not show, such as
+/// the error count of approximate matching and the substituted characters.
+/// Each line is printed as `[path:][line_number:][errors:][fields:][patterns:]line`.
+pub struct AnnotatedPrinter<'a> {
+    matcher: &'a HmgMatcher,
+    only_matching: bool,
+    path: Option<PathBuf>,
+
+    /// The maximum number of matching lines to print, and the number printed so far.
+    max_matches: Option<u64>,
+    match_count: u64,
+
+    /// The number of after context lines still to print, also after the limit is reached.
+    after_context_remaining: u64,
+
+    has_matched: bool,
+
+    /// Finds the substituted characters of each match, and whether to list them.
+    explainer: Option<&'a Explainer>,
+    explain: bool,
+
+    /// The colors of path, line number, match and substitution. The pattern labels are always cyan.
+    colors: Colors,
+
+    /// The line printed between the context groups. None prints nothing.
+    separator: Option<Vec<u8>>,
+    wtr: Buffer,
+}
+
+impl<'a> AnnotatedPrinter<'a> {
+    /// Create a printer that writes to `wtr`. Colors are written only if `wtr` supports them.
+    pub fn new(matcher: &'a HmgMatcher, only_matching: bool, wtr: Buffer) -> Self {
+        AnnotatedPrinter {
+            matcher,
+            only_matching,
+            path: None,
+            max_matches: None,
+            match_count: 0,
+            after_context_remaining: 0,
+            has_matched: false,
+            explainer: None,
+            explain: false,
+            colors: Colors::default(),
+            separator: Some(b"--".to_vec()),
+            wtr,
+        }
+    }
+
+    /// Print `path` at the start of each line.
+    pub fn path(&mut self, path: &Path) -> &mut Self {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Stop the search after `limit` matching lines.
+    pub fn max_matches(&mut self, limit: Option<u64>) -> &mut Self {
+        self.max_matches = limit;
+        self
+    }
+
+    pub fn colors(&mut self, colors: Colors) -> &mut Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Set the line printed between the context groups. None prints nothing.
+    pub fn separator(&mut self, separator: Option<&[u8]>) -> &mut Self {
+        self.separator = separator.map(|s| s.to_vec());
+        self
+    }
+
+    /// Highlight the substituted characters found by `explainer`.
+    /// If `explain` is true, also list them after each matching line.
+    pub fn explainer(&mut self, explainer: &'a Explainer, explain: bool) -> &mut Self {
+        self.explainer = Some(explainer);
+        self.explain = explain;
+        self
+    }
+
+    /// Whether any line has matched.
+    pub fn has_matched(&self) -> bool {
+        self.has_matched
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.wtr.into_inner()
+    }
+
+    fn is_limit_reached(&self) -> bool {
+        self.max_matches.is_some_and(|limit| self.match_count >= limit)
+    }
+
+    /// Whether to stop the search. Like the Standard printer, the after context
+    /// of the last match is printed after the limit is reached.
+    fn should_quit(&self) -> bool {
+        self.is_limit_reached() && self.after_context_remaining == 0
+    }
+
+    fn write_colored(&mut self, spec: &ColorSpec, bytes: &[u8]) -> io::Result<()> {
+        self.wtr.set_color(spec)?;
+        self.wtr.write_all(bytes)?;
+        self.wtr.reset()
+    }
+
+    fn write_prefix(&mut self, line_number: Option<u64>, matches: &[HmgMatch], sep: &[u8]) -> io::Result<()> {
+        if let Some(path) = self.path.clone() {
+            let spec = self.colors.specs.path().clone();
+            self.write_colored(&spec, path.to_string_lossy().as_bytes())?;
+            self.wtr.write_all(sep)?;
+        }
+
+        if let Some(n) = line_number {
+            let spec = self.colors.specs.line().clone();
+            self.write_colored(&spec, n.to_string().as_bytes())?;
+            self.wtr.write_all(sep)?;
+        }
+
+        if let Some(e) = matches.iter().filter_map(|m| m.errors).min() {
+            self.wtr.write_all(e.to_string().as_bytes())?;
+            self.wtr.write_all(sep)?;
+        }
+
+        // labels of the matched JSON fields, in order of appearance.
+        let mut fields: Vec<&str> = vec![];
+        for m in matches {
+            if let Some(label) = m.field.and_then(|i| self.matcher.field_label(i)) {
+                if !fields.contains(&label) {
+                    fields.push(label);
+                }
+            }
+        }
+        if !fields.is_empty() {
+            self.write_colored(&pattern_spec(), fields.join(",").as_bytes())?;
+            self.wtr.write_all(sep)?;
+        }
+
+        // labels of the matched patterns, in order of appearance.
+        let mut labels: Vec<&str> = vec![];
+        for m in matches {
+            if let Some(label) = m.pattern.and_then(|i| self.matcher.pattern_label(i)) {
+                if !labels.contains(&label) {
+                    labels.push(label);
+                }
+            }
+        }
+        if !labels.is_empty() {
+            self.write_colored(&pattern_spec(), labels.join(",").as_bytes())?;
+            self.wtr.write_all(sep)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the text of a match, with the substituted characters in their own color.
+    fn write_match(&mut self, text: &[u8]) -> io::Result<()> {
+        let match_spec = self.colors.specs.matched().clone();
+        let substitution_spec = self.colors.substitution.clone();
+
+        // the substitutions are found in the decoded text, so an escaped match is highlighted as a whole.
+        let substitutions = match self.explainer {
+            Some(explainer) if self.matcher.decode(text) == text => explainer.substitutions(text),
+            _ => vec![],
+        };
+
+        let mut last = 0;
+        for sub in substitutions {
+            if last < sub.start {
+                self.write_colored(&match_spec, &text[last..sub.start])?;
+            }
+            self.write_colored(&substitution_spec, &text[sub.start..sub.end])?;
+            last = sub.end;
+        }
+        if last < text.len() {
+            self.write_colored(&match_spec, &text[last..])?;
+        }
+
+        Ok(())
+    }
+
+    /// With `explain`, list the substituted characters of `text` one per line.
+    fn write_explanation(&mut self, text: &[u8]) -> io::Result<()> {
+        let explainer = match self.explainer {
+            Some(explainer) if self.explain => explainer,
+            _ => return Ok(()),
+        };
+
+        for sub in explainer.substitutions(&self.matcher.decode(text)) {
+            self.wtr.write_all(b"    ")?;
+            self.wtr.write_all(sub.describe().as_bytes())?;
+            self.wtr.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_line(&mut self, line_number: Option<u64>, line: &[u8], matches: &[HmgMatch]) -> io::Result<()> {
+        if self.only_matching {
+            for m in matches {
+                self.write_prefix(line_number, &[*m], b":")?;
+                self.write_match(&line[m.start..m.end])?;
+                self.wtr.write_all(b"\n")?;
+                self.write_explanation(&line[m.start..m.end])?;
+            }
+            return Ok(());
+        }
+
+        self.write_prefix(line_number, matches, b":")?;
+
+        // overlapped matches of multiple patterns are highlighted together.
+        let mut ranges: Vec<(usize, usize)> = vec![];
+        for m in matches {
+            match ranges.last_mut() {
+                Some(last) if m.start < last.1 => last.1 = last.1.max(m.end),
+                _ => ranges.push((m.start, m.end)),
+            }
+        }
+
+        let mut last = 0;
+        for &(start, end) in &ranges {
+            self.wtr.write_all(&line[last..start])?;
+            self.write_match(&line[start..end])?;
+            last = end;
+        }
+        self.wtr.write_all(&line[last..])?;
+        if !line.ends_with(b"\n") {
+            self.wtr.write_all(b"\n")?;
+        }
+
+        for &(start, end) in &ranges {
+            self.write_explanation(&line[start..end])?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Sink for AnnotatedPrinter<'a> {
+    type Error = io::Error;
+
+    fn matched(&mut self, searcher: &Searcher, mat: &SinkMatch) -> Result<bool, io::Error> {
+        self.has_matched = true;
+        let mut line_number = mat.line_number();
+
+        for line in match_lines(searcher, self.matcher, mat) {
+            // a match in the after context of the last match is printed as a part of the context.
+            if self.is_limit_reached() {
+                if self.after_context_remaining == 0 {
+                    break;
+                }
+                self.after_context_remaining -= 1;
+            } else {
+                self.after_context_remaining = searcher.after_context() as u64;
+            }
+
+            let line = strip_terminator(searcher, line);
+            let matches = self.matcher.find_all(line);
+            self.write_line(line_number, line, &matches)?;
+            line_number = line_number.map(|n| n + 1);
+            self.match_count += 1;
+        }
+
+        // returning false stops the search of this file.
+        Ok(!self.should_quit())
+    }
+
+    fn context(&mut self, searcher: &Searcher, context: &SinkContext) -> Result<bool, io::Error> {
+        if *context.kind() == SinkContextKind::After {
+            self.after_context_remaining = self.after_context_remaining.saturating_sub(1);
+        }
+
+        if self.only_matching {
+            return Ok(!self.should_quit());
+        }
+
+        let bytes = strip_terminator(searcher, context.bytes());
+        self.write_prefix(context.line_number(), &[], b"-")?;
+        self.wtr.write_all(bytes)?;
+        if !bytes.ends_with(b"\n") {
+            self.wtr.write_all(b"\n")?;
+        }
+
+        Ok(!self.should_quit())
+    }
+
+    fn context_break(&mut self, _searcher: &Searcher) -> Result<bool, io::Error> {
+        if let Some(separator) = &self.separator {
+            self.wtr.write_all(separator)?;
+            self.wtr.write_all(b"\n")?;
+        }
+        Ok(true)
+    }
+}
+
+/// Sink that breaks the matches into groups, like the context lines do, when no context line is printed.
+/// With GNU grep, `-C0` prints the group separator between matching lines that are not adjacent,
+/// but the searcher reports a break only with one or more context lines.
+pub struct GroupBreakSink<S> {
+    inner: S,
+
+    /// The absolute byte offset at the end of the last matching line(s).
+    last_end: Option<u64>,
+}
+
+impl<S: Sink> GroupBreakSink<S> {
+    pub fn new(inner: S) -> Self {
+        GroupBreakSink { inner, last_end: None }
+    }
+}
+
+impl<S: Sink> Sink for GroupBreakSink<S> {
+    type Error = S::Error;
+
+    fn matched(&mut self, searcher: &Searcher, mat: &SinkMatch) -> Result<bool, S::Error> {
+        let start = mat.absolute_byte_offset();
+        if self.last_end.is_some_and(|end| end < start) && !self.inner.context_break(searcher)? {
+            return Ok(false);
+        }
+        self.last_end = Some(start + mat.bytes().len() as u64);
+
+        self.inner.matched(searcher, mat)
+    }
+
+    fn context(&mut self, searcher: &Searcher, context: &SinkContext) -> Result<bool, S::Error> {
+        self.inner.context(searcher, context)
+    }
+
+    fn context_break(&mut self, searcher: &Searcher) -> Result<bool, S::Error> {
+        self.inner.context_break(searcher)
+    }
+
+    fn binary_data(&mut self, searcher: &Searcher, binary_byte_offset: u64) -> Result<bool, S::Error> {
+        self.inner.binary_data(searcher, binary_byte_offset)
+    }
+
+    fn begin(&mut self, searcher: &Searcher) -> Result<bool, S::Error> {
+        self.inner.begin(searcher)
+    }
+
+    fn finish(&mut self, searcher: &Searcher, finish: &SinkFinish) -> Result<(), S::Error> {
+        self.inner.finish(searcher, finish)
+    }
+}
+
+/// Color of the pattern labels.
+fn pattern_spec() -> ColorSpec {
+    let mut spec = ColorSpec::new();
+    spec.set_fg(Some(Color::Cyan));
+    spec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use grep_searcher::SearcherBuilder;
+    use hmglib::Homoglyphs;
+
+    use super::super::skeleton::SkeletonMatcher;
+
+    #[test]
+    fn tests_after_context_at_max_matches() {
+        let homoglyphs = Homoglyphs::new();
+        let matcher = HmgMatcher::Skeleton(SkeletonMatcher::new(&homoglyphs, "paypal"));
+
+        let mut printer = AnnotatedPrinter::new(&matcher, false, Buffer::no_color());
+        printer.max_matches(Some(1));
+        let mut searcher = SearcherBuilder::new().after_context(2).build();
+        searcher
+            .search_slice(&matcher, "paypal\na\nb\nc\npaypal\n".as_bytes(), &mut printer)
+            .unwrap();
+
+        assert_eq!(String::from_utf8(printer.into_inner()).unwrap(), "1:paypal\n2-a\n3-b\n");
+    }
+
+    #[test]
+    fn tests_group_break_without_context() {
+        let homoglyphs = Homoglyphs::new();
+        let matcher = HmgMatcher::Skeleton(SkeletonMatcher::new(&homoglyphs, "paypal"));
+
+        let mut printer = AnnotatedPrinter::new(&matcher, false, Buffer::no_color());
+        printer.separator(Some(b"--"));
+        SearcherBuilder::new()
+            .build()
+            .search_slice(&matcher, "paypal\na\npaypal\npaypal\n".as_bytes(), GroupBreakSink::new(&mut printer))
+            .unwrap();
+
+        assert_eq!(String::from_utf8(printer.into_inner()).unwrap(), "1:paypal\n--\n3:paypal\n4:paypal\n");
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+// Reference:
+//   - https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use grep_searcher::{Searcher, Sink, SinkMatch};
+use serde_json::{json, Value};
+
+use super::common::{char_indices_lossy, match_lines};
+use super::explain::Explainer;
+use super::matcher::{HmgMatch, HmgMatcher};
+use super::STDIN_LABEL;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const INFORMATION_URI: &str = "https://github.com/blacknon/hmg-utils";
+
+/// Get the rule ID of the pattern at `index`.
+pub fn rule_id(index: usize) -> String {
+    format!("homoglyph/{}", index)
+}
+
+/// Printer of the SARIF results of a file.
+/// The results are written as JSON Lines, to be merged into a single log by `build_log`.
+pub struct SarifPrinter<'a> {
+    matcher: &'a HmgMatcher,
+    explainer: Option<&'a Explainer>,
+    uri: String,
+
+    /// The archive member or the document part searched in the file at `uri`, such as `Sheet1!B2`.
+    part: Option<String>,
+
+    /// The maximum number of matching lines to print, and the number printed so far.
+    max_matches: Option<u64>,
+    match_count: u64,
+
+    wtr: Vec<u8>,
+}
+
+impl<'a> SarifPrinter<'a> {
+    pub fn new(matcher: &'a HmgMatcher, path: &Path) -> Self {
+        SarifPrinter {
+            matcher,
+            explainer: None,
+            uri: path_to_uri(path),
+            part: None,
+            max_matches: None,
+            match_count: 0,
+            wtr: vec![],
+        }
+    }
+
+    /// Stop the search after `limit` matching lines.
+    pub fn max_matches(&mut self, limit: Option<u64>) -> &mut Self {
+        self.max_matches = limit;
+        self
+    }
+
+    /// Locate the results in `name`, a part of the file, as a logical location.
+    pub fn part(&mut self, name: &str) -> &mut Self {
+        self.part = Some(name.to_string());
+        self
+    }
+
+    /// Describe the substituted characters with `explainer`.
+    pub fn explainer(&mut self, explainer: &'a Explainer) -> &mut Self {
+        self.explainer = Some(explainer);
+        self
+    }
+
+    /// Whether any line has matched.
+    pub fn has_matched(&self) -> bool {
+        self.match_count > 0
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.wtr
+    }
+
+    /// Build the result of a match at `start..end` of `line`.
+    fn result(&self, line: &[u8], line_number: u64, offset: u64, m: &HmgMatch) -> Value {
+        let (start, end, index) = (m.start, m.end, m.pattern.unwrap_or(0));
+        let decoded = self.matcher.decode(&line[start..end]);
+        let text = String::from_utf8_lossy(&decoded);
+        let pattern = self.explainer.and_then(|e| e.patterns().get(index));
+
+        let substitutions: Vec<String> = match self.explainer {
+            Some(explainer) => explainer.substitutions(&decoded).iter().map(|s| s.describe()).collect(),
+            None => vec![],
+        };
+
+        let mut message = match pattern {
+            Some(pattern) if !substitutions.is_empty() => {
+                format!("`{}` impersonates `{}`: {}", text, pattern, substitutions.join("; "))
+            }
+            Some(pattern) => format!("`{}` matches `{}`", text, pattern),
+            None => format!("`{}` matches", text),
+        };
+        if let Some(field) = m.field.and_then(|i| self.matcher.field_label(i)) {
+            message.push_str(&format!(" (in `{}`)", field));
+        }
+
+        // columns are counted in code points, as declared by `columnKind`.
+        let start_column = char_indices_lossy(&line[..start]).len() + 1;
+        let end_column = char_indices_lossy(&line[..end]).len() + 1;
+
+        let mut location = json!({
+            "physicalLocation": {
+                "artifactLocation": { "uri": self.uri },
+                "region": {
+                    "startLine": line_number,
+                    "startColumn": start_column,
+                    "endColumn": end_column,
+                    "byteOffset": offset + start as u64,
+                    "byteLength": end - start,
+                    "snippet": { "text": String::from_utf8_lossy(&line[start..end]) },
+                },
+            },
+        });
+        // the region is of the text of the part, not of the bytes of the file.
+        if let Some(part) = &self.part {
+            location["logicalLocations"] = json!([{ "fullyQualifiedName": part, "kind": "member" }]);
+        }
+
+        json!({
+            "ruleId": rule_id(index),
+            "ruleIndex": index,
+            "level": "warning",
+            "message": { "text": message },
+            "locations": [location],
+        })
+    }
+}
+
+impl<'a> Sink for SarifPrinter<'a> {
+    type Error = io::Error;
+
+    fn matched(&mut self, searcher: &Searcher, mat: &SinkMatch) -> Result<bool, io::Error> {
+        let first_line_number = mat.line_number().unwrap_or(0);
+        let mut offset = mat.absolute_byte_offset();
+
+        for (i, line) in match_lines(searcher, self.matcher, mat).into_iter().enumerate() {
+            let line_number = first_line_number + i as u64;
+            if self.max_matches.is_some_and(|limit| self.match_count >= limit) {
+                break;
+            }
+
+            for m in self.matcher.find_all(line) {
+                let result = self.result(line, line_number, offset, &m);
+
+                serde_json::to_writer(&mut self.wtr, &result)?;
+                self.wtr.write_all(b"\n")?;
+            }
+
+            self.match_count += 1;
+            offset += line.len() as u64;
+        }
+
+        // returning false stops the search of this file.
+        Ok(self.max_matches.is_none_or(|limit| self.match_count < limit))
+    }
+}
+
+/// Build a SARIF 2.1.0 log from `results` (JSON Lines of `SarifPrinter`).
+/// Each of `patterns` is a rule.
+pub fn build_log(results: &[u8], patterns: &[String]) -> String {
+    let results: Vec<Value> = results
+        .split(|b| *b == b'\n')
+        .filter(|l| !l.is_empty())
+        .filter_map(|l| serde_json::from_slice(l).ok())
+        .collect();
+
+    let rules: Vec<Value> = patterns
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            json!({
+                "id": rule_id(i),
+                "shortDescription": { "text": format!("Homoglyph of `{}`", p) },
+                "properties": { "pattern": p },
+            })
+        })
+        .collect();
+
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "hmgrep",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": INFORMATION_URI,
+                    "rules": rules,
+                },
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    });
+
+    format!("{}\n", serde_json::to_string_pretty(&log).unwrap())
+}
+
+/// Convert `path` to the URI of an artifact. Relative paths stay relative.
+fn path_to_uri(path: &Path) -> String {
+    if path == Path::new(STDIN_LABEL) {
+        return "stdin".to_string();
+    }
+
+    let text = path.to_string_lossy().replace('\\', "/");
+    let text = text.strip_prefix("./").unwrap_or(&text);
+
+    let mut uri = String::new();
+    if path.is_absolute() {
+        uri.push_str("file://");
+    }
+    for c in text.chars() {
+        match c {
+            ' ' | '#' | '%' | '?' => uri.push_str(&format!("%{:02X}", c as u32)),
+            _ => uri.push(c),
+        }
+    }
+
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use grep_regex::RegexMatcher;
+
+    #[test]
+    fn tests_path_to_uri() {
+        assert_eq!(path_to_uri(Path::new("./src/a b.rs")), "src/a%20b.rs");
+        assert_eq!(path_to_uri(Path::new("/tmp/a.rs")), "file:///tmp/a.rs");
+        assert_eq!(path_to_uri(Path::new(STDIN_LABEL)), "stdin");
+    }
+
+    #[test]
+    fn tests_part_location() {
+        let matcher = HmgMatcher::Regex(RegexMatcher::new("paypal").unwrap());
+        let mut printer = SarifPrinter::new(&matcher, Path::new("book.xlsx"));
+        printer.part("Sheet1!B2");
+
+        let result = printer.result(b"paypal\n", 1, 0, &matcher.find_all(b"paypal\n")[0]);
+        let location = &result["locations"][0];
+        assert_eq!(location["physicalLocation"]["artifactLocation"]["uri"], "book.xlsx");
+        assert_eq!(location["logicalLocations"][0]["fullyQualifiedName"], "Sheet1!B2");
+    }
+}
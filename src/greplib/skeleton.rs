@@ -0,0 +1,167 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+use grep_matcher::{Match, Matcher, NoCaptures, NoError};
+
+use hmglib::Homoglyphs;
+
+use super::common::{char_indices_lossy, find_in_lines};
+
+/// Text converted to the confusable skeleton, with a map back to the offsets of the original bytes.
+pub struct SkeletonText {
+    pub text: String,
+
+    /// Start offset in the original bytes of each byte of `text`.
+    starts: Vec<usize>,
+
+    /// End offset in the original bytes of each byte of `text`.
+    ends: Vec<usize>,
+
+    /// Whether each byte offset of `text` (and `text.len()`) is on a character boundary of the original.
+    boundaries: Vec<bool>,
+}
+
+impl SkeletonText {
+    pub fn new(homoglyphs: &Homoglyphs, bytes: &[u8]) -> Self {
+        let mut text = String::new();
+        let mut starts = vec![];
+        let mut ends = vec![];
+        let mut boundaries = vec![];
+
+        for (start, end, c) in char_indices_lossy(bytes) {
+            let skeleton = homoglyphs.get_skeleton_char(c);
+
+            for i in 0..skeleton.len() {
+                starts.push(start);
+                ends.push(end);
+                boundaries.push(i == 0);
+            }
+            text.push_str(&skeleton);
+        }
+        boundaries.push(true);
+
+        SkeletonText {
+            text,
+            starts,
+            ends,
+            boundaries,
+        }
+    }
+
+    /// Find `pattern` (a skeleton) in this text, and return the range in the original bytes.
+    pub fn find(&self, pattern: &str) -> Option<(usize, usize)> {
+        self.find_iter(pattern).into_iter().next()
+    }
+
+    /// Find all non-overlapping `pattern` (a skeleton) in this text, as ranges in the original bytes.
+    pub fn find_iter(&self, pattern: &str) -> Vec<(usize, usize)> {
+        let mut result = vec![];
+
+        let mut pos = 0;
+        while let Some(i) = self.text[pos..].find(pattern) {
+            let start = pos + i;
+            let next_char = match self.text[start..].chars().next() {
+                Some(c) => start + c.len_utf8(),
+                None => self.text.len() + 1,
+            };
+
+            // a candidate in the middle of a character can overlap a real match, so the next
+            // candidate is searched from the next character, not from the end of this one.
+            match self.to_original_aligned(start, start + pattern.len()) {
+                Some(range) => {
+                    result.push(range);
+                    pos = next_char.max(start + pattern.len());
+                }
+                None => pos = next_char,
+            }
+
+            if pos > self.text.len() {
+                break;
+            }
+        }
+
+        result
+    }
+
+    /// Convert a range of this text to a range of the original bytes, only if the range
+    /// does not start or end in the middle of the skeleton of an original character.
+    pub fn to_original_aligned(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+        if !self.boundaries[start] || !self.boundaries[end] {
+            return None;
+        }
+
+        Some(self.to_original(start, end))
+    }
+
+    /// Convert a range of this text to a range of the original bytes.
+    pub fn to_original(&self, start: usize, end: usize) -> (usize, usize) {
+        let original_start = match self.starts.get(start) {
+            Some(s) => *s,
+            None => self.ends.last().cloned().unwrap_or(0),
+        };
+
+        if start == end {
+            return (original_start, original_start);
+        }
+
+        (original_start, self.ends[end - 1])
+    }
+}
+
+/// Matcher that compares the confusable skeleton of each line with the skeleton of the pattern.
+/// Unlike the alternation regex from `generate_pattern_regex`, its size does not grow with the pattern.
+#[derive(Clone, Debug)]
+pub struct SkeletonMatcher {
+    homoglyphs: Homoglyphs,
+
+    /// The skeleton of the pattern.
+    pattern: String,
+}
+
+impl SkeletonMatcher {
+    pub fn new(homoglyphs: &Homoglyphs, pattern: &str) -> Self {
+        SkeletonMatcher {
+            homoglyphs: homoglyphs.clone(),
+            pattern: homoglyphs.get_skeleton(pattern),
+        }
+    }
+}
+
+impl Matcher for SkeletonMatcher {
+    type Captures = NoCaptures;
+    type Error = NoError;
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, NoError> {
+        // search line by line, so that the work stops at the first matching line.
+        let result = find_in_lines(haystack, at, |line| {
+            SkeletonText::new(&self.homoglyphs, line).find(&self.pattern)
+        });
+
+        Ok(result.map(|(s, e)| Match::new(s, e)))
+    }
+
+    fn new_captures(&self) -> Result<NoCaptures, NoError> {
+        Ok(NoCaptures::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_find_iter_after_misaligned() {
+        // `x` (0..1) has the skeleton `a`, and `y` (1..3) has the skeleton `aa`.
+        // the first candidate `aa` at 0 ends in the middle of `y`, but `y` itself matches.
+        let text = SkeletonText {
+            text: "aaa".to_string(),
+            starts: vec![0, 1, 1],
+            ends: vec![1, 3, 3],
+            boundaries: vec![true, true, false, true],
+        };
+
+        assert_eq!(text.find_iter("aa"), vec![(1, 3)]);
+        assert_eq!(text.find_iter("a"), vec![(0, 1)]);
+    }
+}
@@ -5,20 +5,15 @@
 // macro crate
 #[macro_use]
 extern crate clap;
-extern crate itertools;
-extern crate kana;
-extern crate lazy_static;
-extern crate regex;
-extern crate serde;
-extern crate serde_json;
+extern crate hmg_utils;
 
 // modules
 use clap::{App, AppSettings, Arg};
 use std::env::args;
 
 // local modules
-mod hmglib;
-use hmglib::Homoglyphs;
+use hmg_utils::hmglib;
+use hmg_utils::hmglib::Homoglyphs;
 
 /// Parse args and options function.
 fn build_app() -> clap::App<'static, 'static> {
@@ -96,6 +91,20 @@ fn build_app() -> clap::App<'static, 'static> {
                 .short("W")
                 .long("cjk-width"),
         )
+        // --elongation
+        .arg(
+            Arg::with_name("elongation")
+                .help("Allow each character to repeat, and long-vowel marks after kana.")
+                .long("elongation"),
+        )
+        // --max-elongation=NUM
+        .arg(
+            Arg::with_name("max_elongation")
+                .help("Maximum repeat count of each character with --elongation.")
+                .long("max-elongation")
+                .takes_value(true)
+                .requires("elongation"),
+        )
 }
 
 ///
@@ -111,22 +120,37 @@ fn main() {
 
     let is_japanese_kana = matches.is_present("japanese_kana");
     let is_cjk_width = matches.is_present("cjk_width");
+    let is_elongation = matches.is_present("elongation");
 
-    // Get pattern from Homoglyphs object.
-    let pattern = Homoglyphs::new()
+    // Get Homoglyphs object.
+    let mut homoglyphs = Homoglyphs::new();
+    homoglyphs
         .is_ignore_case(is_ignore)
         .is_literal(is_literal)
         .with_japanese_kana(is_japanese_kana)
         .with_cjk_width(is_cjk_width)
-        .get_pattern(text.unwrap());
+        .with_elongation(is_elongation);
+
+    if matches.is_present("max_elongation") {
+        let max_elongation =
+            value_t!(matches, "max_elongation", usize).unwrap_or_else(|e| e.exit());
+        if max_elongation < 1 {
+            let message = "The value of --max-elongation must be 1 or more";
+            clap::Error::with_description(message, clap::ErrorKind::InvalidValue).exit();
+        }
+        homoglyphs.max_elongation(max_elongation);
+    }
 
     // if add -e flag, output regex pattern.
-    if is_regex {
-        let regex_pattern = hmglib::generate_pattern_regex(pattern.clone());
-        println!("{}", regex_pattern);
+    // elongated patterns can not be listed, so they are always output as regex.
+    if is_regex || is_elongation {
+        println!("{}", homoglyphs.get_pattern_regex(text.unwrap()));
         return;
     }
 
+    // Get pattern from Homoglyphs object.
+    let pattern = homoglyphs.get_alternatives(text.unwrap());
+
     let data = hmglib::generate_pattern_list(pattern);
     for d in data {
         println!("{}", d);
@@ -7,7 +7,7 @@
 ///
 /// # Example
 ///
-/// ```
+/// ```ignore
 /// let partial_product = vec![vec![1, 4], vec![1, 5], vec![2, 4], vec![2, 5]];
 /// let items = &[6, 7];
 /// let next_product = partial_cartesian(partial_product, items);
@@ -40,7 +40,7 @@ fn partial_cartesian<T: Clone>(a: Vec<Vec<T>>, b: &[T]) -> Vec<Vec<T>> {
 ///
 /// # Example
 ///
-/// ```
+/// ```ignore
 /// let lists: &[&[_]] = &[&["a"], &["a", "b", "c"], &["a", "b", "c"]];
 /// let product = cartesian_product(lists);
 /// assert_eq!(product, vec![vec!["a","a","a"],
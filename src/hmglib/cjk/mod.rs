@@ -14,6 +14,10 @@ use self::width::get_data_width;
 
 use super::common::SplitChar;
 
+/// Japanese long-vowel marks that may follow a kana when elongating.
+/// ("ー", "～", "〜", "ｰ")
+pub const LONG_VOWEL_MARKS: [&str; 4] = ["\u{30fc}", "\u{ff5e}", "\u{301c}", "\u{ff70}"];
+
 #[derive(Clone, Copy)]
 pub enum SplitType {
     Kana,
@@ -51,6 +55,16 @@ pub fn get_jp_splitchar(split_type: SplitType, chars: Vec<SplitChar>) -> Vec<Spl
     return result;
 }
 
+/// Check whether `c` is Hiragana, Katakana or half-width Katakana.
+pub fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{309f}' | '\u{30a0}'..='\u{30ff}' | '\u{ff66}'..='\u{ff9f}')
+}
+
+/// Fold `c` to the first character of its correspondence (e.g. "カ" => "か").
+pub fn fold_char(split_type: SplitType, c: &str) -> String {
+    check_data(split_type, c).remove(0)
+}
+
 ///
 fn check_data(split_type: SplitType, c: &str) -> Vec<String> {
     let mut result = vec![];
@@ -16,9 +16,11 @@ pub struct HomoglyphData {
     pub data: Vec<String>,
 }
 
-pub fn get_homoglyphs() -> Vec<HomoglyphData> {
-    // json to Vec in struct
-    let data: Vec<HomoglyphData> = serde_json::from_str(DATA_JSON_HOMOGLYPHS).unwrap();
+lazy_static::lazy_static! {
+    // json to Vec in struct. Parsed only once.
+    static ref HOMOGLYPHS: Vec<HomoglyphData> = serde_json::from_str(DATA_JSON_HOMOGLYPHS).unwrap();
+}
 
-    return data;
+pub fn get_homoglyphs() -> &'static [HomoglyphData] {
+    &HOMOGLYPHS
 }
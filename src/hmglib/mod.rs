@@ -20,12 +20,13 @@ mod cartesian;
 mod cjk;
 mod common;
 mod homoglyph;
+mod skeleton;
 
 // use self
 use self::common::SplitChar;
 
 /// Struct for homograph conversion.
-#[derive(Default)]
+#[derive(Default, Clone, Debug)]
 pub struct Homoglyphs {
     // is_literal is enabled,
     is_literal: bool,
@@ -41,6 +42,15 @@ pub struct Homoglyphs {
 
     //
     with_cjk_width: bool,
+
+    // with_elongation is enabled, each character is allowed to repeat.
+    with_elongation: bool,
+
+    // Maximum repeat count of a character when with_elongation is enabled.
+    max_elongation: Option<usize>,
+
+    // with_line_break is enabled, a word wrapped at the end of a line is matched.
+    with_line_break: bool,
 }
 
 /// Impl for homograph conversion
@@ -82,7 +92,38 @@ impl Homoglyphs {
     }
 
     //
+    pub fn with_elongation(&mut self, yes: bool) -> &mut Self {
+        self.with_elongation = yes;
+        return self;
+    }
+
+    //
+    pub fn max_elongation(&mut self, num: usize) -> &mut Self {
+        self.max_elongation = Some(num);
+        return self;
+    }
+
+    //
+    pub fn with_line_break(&mut self, yes: bool) -> &mut Self {
+        self.with_line_break = yes;
+        return self;
+    }
+
+    /// Get the regex alternatives of each character of `plane`.
+    /// An alternative with regex metacharacters, such as `|` (a homoglyph of `l`), is escaped.
     pub fn get_pattern(&self, plane: &str) -> Vec<Vec<String>> {
+        self.get_alternatives(plane)
+            .into_iter()
+            .map(|p| {
+                p.into_iter()
+                    .map(|s| if s.chars().any(is_meta_character) { regex::escape(&s) } else { s })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Get the homoglyphs of each character of `plane`, as plain (not escaped) text.
+    pub fn get_alternatives(&self, plane: &str) -> Vec<Vec<String>> {
         let mut text = plane.to_string();
 
         //
@@ -116,8 +157,7 @@ impl Homoglyphs {
             if c.is_escape {
                 let mut push_data: Vec<String> = vec![];
                 for ch in c.char {
-                    let mut char_data = vec![ch.escape_default().to_string()];
-                    push_data.append(&mut char_data);
+                    push_data.push(ch);
                 }
                 result.push(push_data)
             } else {
@@ -139,8 +179,65 @@ impl Homoglyphs {
 
         return result;
     }
+
+    /// Get the regex pattern of `plane`, applying the elongation and the line break modes if enabled.
+    pub fn get_pattern_regex(&self, plane: &str) -> String {
+        let mut pattern = self.get_pattern(plane);
+
+        if self.with_line_break {
+            // a line break (with a hyphen before it and an indent after it) may follow each character but the last.
+            let last = pattern.len().saturating_sub(1);
+            for p in &mut pattern[..last] {
+                for s in p.iter_mut() {
+                    s.push_str(LINE_BREAK_REGEX);
+                }
+            }
+        }
+
+        if self.with_elongation {
+            generate_elongation_regex(pattern, self.max_elongation)
+        } else {
+            generate_pattern_regex(pattern)
+        }
+    }
+
+    /// Get the confusable skeleton of `plane`.
+    /// Two strings that are homoglyphs of each other have the same skeleton.
+    pub fn get_skeleton(&self, plane: &str) -> String {
+        plane.chars().map(|c| self.get_skeleton_char(c)).collect()
+    }
+
+    /// Get the confusable skeleton of a single character, applying the case,
+    /// width and kana options in the same order as `get_pattern`.
+    pub fn get_skeleton_char(&self, c: char) -> String {
+        let text = self.get_normalized_char(c);
+        text.chars().map(skeleton::skeleton_char).collect()
+    }
+
+    /// Apply only the case, width and kana options to a single character.
+    /// Unlike `get_skeleton_char`, homoglyphs are not folded.
+    pub fn get_normalized_char(&self, c: char) -> String {
+        let mut text = c.to_string();
+
+        if self.is_ignore_case {
+            text = text.to_lowercase();
+        }
+
+        if self.with_cjk_width {
+            text = cjk::fold_char(cjk::SplitType::HalfFullWidth, &text);
+        }
+
+        if self.with_japanese_kana {
+            text = cjk::fold_char(cjk::SplitType::Kana, &text);
+        }
+
+        text
+    }
 }
 
+/// Regex of a line break in a wrapped word.
+const LINE_BREAK_REGEX: &str = r"(?:-?\r?\n[ \t]*)?";
+
 ///
 pub fn generate_pattern_regex(pattern: Vec<Vec<String>>) -> String {
     let mut regex_text = vec![];
@@ -160,6 +257,30 @@ pub fn generate_pattern_regex(pattern: Vec<Vec<String>>) -> String {
     return regex_text.join("");
 }
 
+/// Generate a regex in which every position may repeat (e.g. `ちんんんこ`, `chiiiinkooo`).
+/// Japanese long-vowel marks are also allowed after any kana position (e.g. `ちーーんこ`).
+/// If `max_repeat` is set, each repeat is limited to that count.
+pub fn generate_elongation_regex(pattern: Vec<Vec<String>>, max_repeat: Option<usize>) -> String {
+    let (repeat, optional_repeat) = match max_repeat {
+        Some(num) => (format!("{{1,{}}}", num), format!("{{0,{}}}", num)),
+        None => ("+".to_string(), "*".to_string()),
+    };
+
+    let mut regex_text = vec![];
+    for p in pattern {
+        let is_kana = p.iter().any(|s| s.chars().any(cjk::is_kana));
+
+        regex_text.push(format!("(?:{}){}", p.join("|"), repeat));
+
+        if is_kana {
+            let marks = cjk::LONG_VOWEL_MARKS.join("|");
+            regex_text.push(format!("(?:{}){}", marks, optional_repeat));
+        }
+    }
+
+    regex_text.join("")
+}
+
 ///
 pub fn generate_pattern_list(pattern: Vec<Vec<String>>) -> Vec<String> {
     // TODO: 並列数を指定して処理を実行させる
@@ -177,7 +298,7 @@ fn contains_hmg_text(pchar: String) -> Vec<String> {
     let lines = homoglyph::get_homoglyphs();
 
     // for loop check
-    for l in &lines {
+    for l in lines {
         let mut data = l.data.clone();
         if data.contains(&pchar) {
             result.append(&mut data);
@@ -196,7 +317,7 @@ fn contains_hmg_text(pchar: String) -> Vec<String> {
             let mut is_hit = false;
 
             // for loop check
-            for l in &lines {
+            for l in lines {
                 let data = l.data.clone();
                 if data.contains(&c.to_string()) {
                     chars_data.push(data);
@@ -250,6 +371,11 @@ fn split_regex2schar(regex_text: &str) -> Vec<SplitChar> {
     return result;
 }
 
+// Whether `c` has a meaning in a regex, and must be escaped to be matched as it is.
+fn is_meta_character(c: char) -> bool {
+    matches!(c, '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' | '#' | '&' | '-' | '~')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -270,4 +396,53 @@ mod tests {
         assert_eq!(dakuten1.len(), 2);
         assert_eq!(dakuten2.len(), 6);
     }
+
+    #[test]
+    fn tests_elongation_regex() {
+        let pattern = Homoglyphs::new()
+            .with_elongation(true)
+            .get_pattern_regex("ちんこ");
+        let re = regex::Regex::new(&pattern).unwrap();
+        assert!(re.is_match("ちーーんこ"));
+        assert!(re.is_match("ちんんんこ"));
+        assert!(re.is_match("ちんこ〜"));
+
+        let pattern = Homoglyphs::new()
+            .with_elongation(true)
+            .max_elongation(2)
+            .get_pattern_regex("ちんこ");
+        let re = regex::Regex::new(&pattern).unwrap();
+        assert!(re.is_match("ちんんこ"));
+        assert!(!re.is_match("ちんんんこ"));
+    }
+
+    #[test]
+    fn tests_line_break_regex() {
+        let pattern = Homoglyphs::new().with_line_break(true).get_pattern_regex("paypal");
+        let re = regex::Regex::new(&pattern).unwrap();
+        assert!(re.is_match("pay\npal"));
+        assert!(re.is_match("pay-\r\n  p\u{0430}l"));
+        assert!(!re.is_match("pay\n\npal"));
+        assert_eq!(re.find("paypal\n").unwrap().as_str(), "paypal");
+
+        // `|`, a homoglyph of `l`, must not split the regex.
+        assert!(!re.is_match("pay"));
+    }
+
+    #[test]
+    fn tests_escaped_pattern_regex() {
+        let pattern = Homoglyphs::new().get_pattern_regex("l.");
+        let re = regex::Regex::new(&pattern).unwrap();
+        assert!(re.is_match("|."));
+        assert!(!re.is_match("lx"));
+        assert!(!re.is_match("x."));
+    }
+
+    #[test]
+    fn tests_get_skeleton() {
+        let hmg = Homoglyphs::new();
+        assert_eq!(hmg.get_skeleton("ちんこ"), hmg.get_skeleton("ㄘんこ"));
+        assert_eq!(hmg.get_skeleton("paypal"), hmg.get_skeleton("p\u{0430}ypal"));
+        assert_ne!(hmg.get_skeleton("paypal"), hmg.get_skeleton("paypol"));
+    }
 }
@@ -0,0 +1,45 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+// Reference:
+//   - https://www.unicode.org/reports/tr39/#def-skeleton
+
+use std::collections::HashMap;
+
+use super::homoglyph::get_homoglyphs;
+
+lazy_static::lazy_static! {
+    /// Map each character to the representative of the first homoglyph group
+    /// containing it. This is the same group that `contains_hmg_text` uses.
+    /// The representative is a single character when possible, so that a skeleton
+    /// never matches in the middle of another character.
+    static ref SKELETON_MAP: HashMap<char, String> = {
+        let mut map = HashMap::new();
+
+        for group in get_homoglyphs() {
+            let representative = match group.data.iter().find(|d| d.chars().count() == 1) {
+                Some(d) => d.clone(),
+                None => group.data[0].clone(),
+            };
+
+            for data in &group.data {
+                let mut chars = data.chars();
+                if let (Some(c), None) = (chars.next(), chars.next()) {
+                    map.entry(c).or_insert_with(|| representative.clone());
+                }
+            }
+        }
+
+        map
+    };
+}
+
+/// Get the skeleton of a single character.
+/// Characters that have no homoglyph are their own skeleton.
+pub fn skeleton_char(c: char) -> String {
+    match SKELETON_MAP.get(&c) {
+        Some(representative) => representative.clone(),
+        None => c.to_string(),
+    }
+}
@@ -2,41 +2,34 @@
 // Use of this source code is governed by an MIT license
 // that can be found in the LICENSE file.
 
-// TODO: sjisやeuc-jpなど、複数の文字コードでgrepできるようにする
-//       【参考】
-//         - https://github.com/lifthrasiir/rust-encoding
-
 // macro crate
 #[macro_use]
 extern crate clap;
-extern crate bytes;
-extern crate crossbeam;
-extern crate grep;
-extern crate grep_matcher;
-extern crate grep_regex;
-extern crate grep_searcher;
-extern crate ignore;
-extern crate itertools;
-extern crate kana;
-extern crate lazy_static;
-extern crate memchr;
-extern crate rayon;
-extern crate regex;
-extern crate serde;
-extern crate serde_json;
-extern crate termcolor;
+extern crate hmg_utils;
 
 // modules
-use clap::{App, AppSettings, Arg};
-use std::env::args;
-use std::path::PathBuf;
+use clap::{App, AppSettings, Arg, ArgSettings};
+use std::env::{self, args};
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process;
 use std::str;
 
 // local modules
-mod greplib;
-mod hmglib;
+use hmg_utils::greplib;
+use hmg_utils::hmglib;
 
-use greplib::scan;
+use greplib::approx::{ApproxCosts, ApproxMatcher};
+use greplib::blocklist::Blocklist;
+use greplib::csvcolumn::{Column, CsvColumnMatcher};
+use greplib::decode::{DecodeMatcher, Decoding};
+use greplib::explain::Explainer;
+use greplib::jsonfield::{FieldPath, JsonFieldMatcher};
+use greplib::matcher::{HmgMatcher, Pattern};
+use greplib::skeleton::SkeletonMatcher;
+use greplib::{ColorMode, OutputMode, SortMode};
 use hmglib::Homoglyphs;
 
 /// Parse args and options function.
@@ -60,27 +53,193 @@ fn build_app() -> clap::App<'static, 'static> {
         // -- OPTIONS --
         // Approximate matching settings:
         // -p, --approximate
+        .arg(
+            Arg::with_name("approximate")
+                .help("Approximate matching. Homoglyph substitutions cost --homoglyph-cost.")
+                .short("p")
+                .long("approximate"),
+        )
         // -b, --best-match
+        .arg(
+            Arg::with_name("best_match")
+                .help("Select only the lines with the lowest error count.")
+                .short("b")
+                .long("best-match"),
+        )
         // -D, --delete-cost=NUM     set cost of missing characters
+        .arg(
+            Arg::with_name("delete_cost")
+                .help("Set cost of missing characters.")
+                .short("D")
+                .long("delete-cost")
+                .takes_value(true)
+                .default_value("1"),
+        )
         // -I, --insert-cost=NUM     set cost of extra characters
+        .arg(
+            Arg::with_name("insert_cost")
+                .help("Set cost of extra characters.")
+                .short("I")
+                .long("insert-cost")
+                .takes_value(true)
+                .default_value("1"),
+        )
         // -S, --substitute-cost=NUM set cost of wrong characters
-        // -E, --max-errors=NUM     select records that have at most NUM errors
+        .arg(
+            Arg::with_name("substitute_cost")
+                .help("Set cost of wrong characters.")
+                .short("S")
+                .long("substitute-cost")
+                .takes_value(true)
+                .default_value("1"),
+        )
+        // --homoglyph-cost=NUM      set cost of homoglyph characters
+        .arg(
+            Arg::with_name("homoglyph_cost")
+                .help("Set cost of homoglyph characters.")
+                .long("homoglyph-cost")
+                .takes_value(true)
+                .default_value("0"),
+        )
+        //     --max-errors=NUM     select records that have at most NUM errors
+        //   ... long only, `-E` is `--encoding` like ugrep.
+        .arg(
+            Arg::with_name("max_errors")
+                .help("Select records that have at most NUM errors. [default: 1]")
+                .long("max-errors")
+                .takes_value(true),
+        )
         // -#			    select records that have at most # errors (# is a
         // 　			     digit between 0 and 9)
+        //   ... not implemented, conflicts with `-NUM` of context control.
         //
         // Pattern selection and interpretation:
-        // -e, --regexp // gnu grepと同じように、要素単位のオプションにするか？
+        // -e, --regexp
+        .arg(
+            Arg::with_name("regexp")
+                .help("Use PATTERN for matching. Can be given multiple times.")
+                .short("e")
+                .long("regexp")
+                .value_name("PATTERN")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .allow_hyphen_values(true),
+        )
         // -f, --file
+        .arg(
+            Arg::with_name("file")
+                .help("Read patterns from FILE, one per line. Lines starting with `#` are comments.")
+                .short("f")
+                .long("file")
+                .value_name("FILE")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        // --blocklist=FILE
+        .arg(
+            Arg::with_name("blocklist")
+                .help("Report every word of FILE (one per line) that hits each line. Scales to thousands of words.")
+                .long("blocklist")
+                .value_name("FILE")
+                .takes_value(true)
+                .conflicts_with_all(&["regexp", "file", "approximate", "max_errors", "best_match", "elongation"]),
+        )
         // -i, --ignore-case
         .arg(Arg::with_name("ignore_case").short("i"))
         // -k, --literal
         // -w, --word-regexp
         // -z, --null-data
+        .arg(
+            Arg::with_name("null_data")
+                .help("Search NUL-terminated records instead of lines. Each record is printed in a line.")
+                .short("z")
+                .long("null-data"),
+        )
         // -W, --with-word
-        // -u, --multiunicode
-        //   ... 他の文字コードで検索できるようにする
-        // -H, --disable-homoglyphs
+        // -E, --encoding=ENCODING
+        .arg(
+            Arg::with_name("encoding")
+                .help("Transcode the input from ENCODING (e.g. sjis, euc-jp, iso-2022-jp, utf-16le) to UTF-8 before searching. `none` disables BOM detection. [default: auto, UTF-8 or by BOM]")
+                .short("E")
+                .long("encoding")
+                .value_name("ENCODING")
+                .takes_value(true),
+        )
+        //     --disable-homoglyphs
         //   ... ホモグリフ変換を無効化
+        // --json-field=PATH
+        .arg(
+            Arg::with_name("json_field")
+                .help("Search only the string values of the field PATH (e.g. `.message`, `.user.name`) of each JSON line. The strings are decoded before matching. Can be given multiple times.")
+                .long("json-field")
+                .value_name("PATH")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        // --csv
+        .arg(
+            Arg::with_name("csv")
+                .help("Parse the input as CSV (RFC 4180), and print each matching record in a line. `*.tsv` files are separated by tabs.")
+                .long("csv")
+                .conflicts_with("json_field"),
+        )
+        // --tsv
+        .arg(
+            Arg::with_name("tsv")
+                .help("Parse the input as CSV separated by tabs.")
+                .long("tsv")
+                .conflicts_with("json_field"),
+        )
+        // --column=NAME|INDEX
+        .arg(
+            Arg::with_name("column")
+                .help("With --csv, search only the column NAME in the header, or the column INDEX (1-based). Implies --csv. Can be given multiple times.")
+                .long("column")
+                .value_name("NAME|INDEX")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .conflicts_with("json_field"),
+        )
+        // --no-header
+        .arg(
+            Arg::with_name("no_header")
+                .help("With --csv, the first record is not the header. Only INDEX can be used by --column.")
+                .long("no-header"),
+        )
+        // --documents
+        .arg(
+            Arg::with_name("documents")
+                .help("Extract the text of office documents (docx, xlsx, pptx, odt, ods and odp) before searching. Each match is printed with the slide, or the sheet and the cell like `book.xlsx!Sheet1!C3`.")
+                .long("documents"),
+        )
+        // --mail
+        .arg(
+            Arg::with_name("mail")
+                .help("Parse every file as mbox or a message, and search the decoded headers and text bodies. Each match is printed with the Message-ID and the header name.")
+                .long("mail")
+                .conflicts_with_all(&["csv", "tsv", "column"]),
+        )
+        // --decode=LIST
+        .arg(
+            Arg::with_name("decode")
+                .help("Decode each line with LIST, comma-separated decodings applied in order, before matching. Matches are printed as the raw text.")
+                .long("decode")
+                .value_name("LIST")
+                .takes_value(true)
+                .use_delimiter(true)
+                .possible_values(&["html", "url", "punycode", "qp"]),
+        )
+        // --skeleton
+        .arg(
+            Arg::with_name("skeleton")
+                .help("Search by confusable skeleton instead of regex. Used automatically for too large patterns.")
+                .long("skeleton")
+                .conflicts_with("elongation"),
+        )
         // -j, --japanese-kana
         .arg(
             Arg::with_name("japanese_kana")
@@ -94,37 +253,259 @@ fn build_app() -> clap::App<'static, 'static> {
                 .short("W")
                 .long("cjk-width"),
         )
+        // --elongation
+        .arg(
+            Arg::with_name("elongation")
+                .help("Allow each character to repeat, and long-vowel marks after kana.")
+                .long("elongation"),
+        )
+        // --max-elongation=NUM
+        .arg(
+            Arg::with_name("max_elongation")
+                .help("Maximum repeat count of each character with --elongation.")
+                .long("max-elongation")
+                .takes_value(true)
+                .requires("elongation"),
+        )
         // Approximate matching settings:
         //
         // Miscellaneous:
         // -d, --delimiter
+        .arg(
+            Arg::with_name("delimiter")
+                .help("Search records separated by DELIM instead of lines, such as `\\n\\n` for paragraphs. `\\n`, `\\r`, `\\t`, `\\0` and `\\xHH` are escapes.")
+                .short("d")
+                .long("delimiter")
+                .value_name("DELIM")
+                .takes_value(true)
+                .conflicts_with("null_data"),
+        )
         // -s, --no-messages
         // -v, --invert-match
+        .arg(
+            Arg::with_name("invert_match")
+                .help("Select non-matching lines.")
+                .short("v")
+                .long("invert-match"),
+        )
         // -V, --version
         //
         // Output control:
         // -m, --max-count=NUM
-        // .arg(Arg::with_name("max_count").short("m").default_value("0"))
+        .arg(
+            Arg::with_name("max_count")
+                .help("Stop reading a file after NUM matching lines.")
+                .short("m")
+                .long("max-count")
+                .value_name("NUM")
+                .takes_value(true),
+        )
         // -n, --line-number
         .arg(Arg::with_name("line_number").short("n"))
         //     --line-buffered
-        //     --with-filename
+        // -H, --with-filename
+        .arg(
+            Arg::with_name("with_filename")
+                .help("Print the file name for each match. Default when searching multiple files.")
+                .short("H")
+                .long("with-filename")
+                .overrides_with("no_filename"),
+        )
         //     --no-filename
+        .arg(
+            Arg::with_name("no_filename")
+                .help("Suppress the file name prefix on output.")
+                .long("no-filename")
+                .overrides_with("with_filename"),
+        )
         //     --label=LABEL
         // -o, --only-matching
         .arg(Arg::with_name("only_matching").short("o"))
+        //     --format=FORMAT
+        .arg(
+            Arg::with_name("format")
+                .help("Output format. `json` is JSON Lines (same as --json), `sarif` is a SARIF 2.1.0 log.")
+                .long("format")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .possible_values(&["text", "json", "sarif"])
+                .conflicts_with_all(&["files_with_matches", "files_without_match", "count", "quiet", "only_matching"]),
+        )
+        //     --json
+        .arg(
+            Arg::with_name("json")
+                .help("Print the matches as JSON Lines, with the pattern character of each matched character.")
+                .long("json")
+                .conflicts_with_all(&["format", "files_with_matches", "files_without_match", "count", "quiet", "only_matching"]),
+        )
+        //     --explain
+        .arg(
+            Arg::with_name("explain")
+                .help("List each substituted character of a match, with its code point and Unicode name.")
+                .long("explain"),
+        )
         // -q, --quiet, --silent
+        .arg(
+            Arg::with_name("quiet")
+                .help("Print nothing. Exit with 0 on the first match.")
+                .short("q")
+                .long("quiet")
+                .alias("silent"),
+        )
         //     --binary-files=TYPE
         // -a, --text
+        //     --sort=SORTBY
+        .arg(
+            Arg::with_name("sort")
+                .help("Sort the output files by path or modified time. `none` prints each file as soon as it is searched. [default: walk order, by file name in each directory]")
+                .long("sort")
+                .value_name("SORTBY")
+                .takes_value(true)
+                .possible_values(&["path", "modified", "none"]),
+        )
+        //     --files-from=FILE
+        .arg(
+            Arg::with_name("files_from")
+                .help("Read the list of paths to search from FILE, one per line. `-` reads it from stdin.")
+                .long("files-from")
+                .value_name("FILE")
+                .takes_value(true),
+        )
         // -r, --recursive
+        .arg(
+            Arg::with_name("recursive")
+                .help("Search directories recursively. This is the default, accepted for grep compatibility.")
+                .short("r")
+                .long("recursive"),
+        )
         // -R, --dereference-recursive
+        .arg(
+            Arg::with_name("dereference_recursive")
+                .help("Search directories recursively, following all symbolic links.")
+                .short("R")
+                .long("dereference-recursive"),
+        )
         //     --include=GLOB
+        .arg(
+            Arg::with_name("include")
+                .help("Search only files whose name matches GLOB. Can be given multiple times.")
+                .long("include")
+                .value_name("GLOB")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
         //     --exclude=GLOB
+        .arg(
+            Arg::with_name("exclude")
+                .help("Skip files whose name matches GLOB. Can be given multiple times.")
+                .long("exclude")
+                .value_name("GLOB")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
         //     --exclude-from=FILE
+        .arg(
+            Arg::with_name("exclude_from")
+                .help("Skip files whose name matches any glob in FILE, one per line.")
+                .long("exclude-from")
+                .value_name("FILE")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
         //     --exclude-dir=GLOB
-        //     --files-without-match
-        //     --files-with-matches
+        .arg(
+            Arg::with_name("exclude_dir")
+                .help("Skip directories whose name matches GLOB. Can be given multiple times.")
+                .long("exclude-dir")
+                .value_name("GLOB")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        //     --hidden
+        .arg(
+            Arg::with_name("hidden")
+                .help("Search hidden files and directories.")
+                .long("hidden"),
+        )
+        //     --no-ignore
+        .arg(
+            Arg::with_name("no_ignore")
+                .help("Don't respect ignore files (.gitignore, .ignore, etc.).")
+                .long("no-ignore"),
+        )
+        //     --search-zip (long only, `-z` is `--null-data` like GNU grep)
+        .arg(
+            Arg::with_name("search_zip")
+                .help("Search in compressed files (gzip, xz, bzip2 and zstd). Long only: -z is --null-data.")
+                .long("search-zip"),
+        )
+        //     --max-depth=NUM
+        .arg(
+            Arg::with_name("max_depth")
+                .help("Descend at most NUM directories below the given paths.")
+                .long("max-depth")
+                .value_name("NUM")
+                .takes_value(true),
+        )
+        //     --follow
+        .arg(
+            Arg::with_name("follow")
+                .help("Follow symbolic links.")
+                .long("follow"),
+        )
+        //     --max-filesize=NUM[K|M|G]
+        .arg(
+            Arg::with_name("max_filesize")
+                .help("Skip files larger than NUM bytes. Suffixes K, M and G are allowed.")
+                .long("max-filesize")
+                .value_name("NUM")
+                .takes_value(true),
+        )
+        //     --max-member-size=NUM[K|M|G]
+        .arg(
+            Arg::with_name("max_member_size")
+                .help("With --search-zip, skip archive members larger than NUM bytes after decompression. With --documents, a document part (such as a sheet) larger than NUM is an error. Suffixes K, M and G are allowed.")
+                .long("max-member-size")
+                .value_name("NUM")
+                .takes_value(true)
+                .default_value("100M"),
+        )
+        //     --max-archive-depth=NUM
+        .arg(
+            Arg::with_name("max_archive_depth")
+                .help("With --search-zip, descend at most NUM levels of archives in archives.")
+                .long("max-archive-depth")
+                .value_name("NUM")
+                .takes_value(true)
+                .default_value("2"),
+        )
+        // -L, --files-without-match
+        .arg(
+            Arg::with_name("files_without_match")
+                .help("Print only the names of files without a match.")
+                .short("L")
+                .long("files-without-match")
+                .conflicts_with_all(&["files_with_matches", "count"]),
+        )
+        // -l, --files-with-matches
+        .arg(
+            Arg::with_name("files_with_matches")
+                .help("Print only the names of files with a match.")
+                .short("l")
+                .long("files-with-matches")
+                .conflicts_with("count"),
+        )
         // -c, --count
+        .arg(
+            Arg::with_name("count")
+                .help("Print only the number of matching lines of each file.")
+                .short("c")
+                .long("count"),
+        )
         // -T, --initial-tab
         // -Z, --null
         //
@@ -132,87 +513,687 @@ fn build_app() -> clap::App<'static, 'static> {
         // -B, --before-context=NUM
         .arg(
             Arg::with_name("before_context")
+                .help("Print NUM lines of leading context.")
                 .short("B")
-                .takes_value(true)
-                .default_value("0"),
+                .long("before-context")
+                .value_name("NUM")
+                .takes_value(true),
         )
         // -A, --after-context=NUM
         .arg(
             Arg::with_name("after_context")
+                .help("Print NUM lines of trailing context.")
                 .short("A")
-                .takes_value(true)
-                .default_value("0"),
+                .long("after-context")
+                .value_name("NUM")
+                .takes_value(true),
         )
         // -C, --context=NUM
         // -NUM
+        .arg(
+            Arg::with_name("context")
+                .help("Print NUM lines of leading and trailing context. `-NUM` is the same. -A and -B take priority.")
+                .short("C")
+                .long("context")
+                .value_name("NUM")
+                .takes_value(true),
+        )
+        // --group-separator=SEP
+        .arg(
+            Arg::with_name("group_separator")
+                .help("Print SEP between the groups of context lines. [default: --]")
+                .long("group-separator")
+                .value_name("SEP")
+                .takes_value(true),
+        )
+        // --no-group-separator
+        .arg(
+            Arg::with_name("no_group_separator")
+                .help("Print nothing between the groups of context lines.")
+                .long("no-group-separator")
+                .conflicts_with("group_separator"),
+        )
+        // --passthru
+        .arg(
+            Arg::with_name("passthru")
+                .help("Print every line, and highlight the matches. Useful to review a whole document.")
+                .long("passthru")
+                .alias("passthrough")
+                .conflicts_with_all(&["only_matching", "invert_match"]),
+        )
         // -P, --parallel
         // --color
         // --colour
-        // .arg(
-        //     Arg::with_name("color")
-        //         .help("use markers to highlight the matching strings")
-        //         .long("color")
-        //         .long("colour")
-        //         .takes_value(true)
-        //         .default_value("auto")
-        //         .possible_values(&["always", "auto", "never"]),
-        // )
-        // -U, --binary
+        .arg(
+            Arg::with_name("color")
+                .help("Use markers to highlight the matching strings. `auto` colors only if stdout is a terminal.")
+                .long("color")
+                .alias("colour")
+                .value_name("WHEN")
+                .takes_value(true)
+                .default_value("auto")
+                .possible_values(&["always", "auto", "never"]),
+        )
+        // --colors=SPEC
+        .arg(
+            Arg::with_name("colors")
+                .help("Set the color of path, line, match or substitution, like `match:fg:blue` or `substitution:style:bold`. Overrides GREP_COLORS.")
+                .long("colors")
+                .value_name("SPEC")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        // -U, --multiline
+        .arg(
+            Arg::with_name("multiline")
+                .help("Allow matches across lines. A word wrapped at the end of a line (also after a hyphen) is matched.")
+                .short("U")
+                .long("multiline"),
+        )
         // -- PATTERNS --
         .arg(
             Arg::with_name("PATTERNS")
                 .allow_hyphen_values(true)
                 .multiple(false)
-                .required(true),
+                .required_unless_one(&["regexp", "file", "blocklist"]),
         )
         // -- PATH --
         .arg(
             Arg::with_name("PATH")
+                .help("Files or directories to search. `-` or no PATH reads stdin.")
                 .allow_hyphen_values(true)
-                .multiple(true)
-                .required(true),
+                .multiple(true),
         )
 }
 
+/// Read patterns from `path`, one per line.
+/// Empty lines and lines starting with `#` are skipped.
+fn read_pattern_file(path: &str) -> io::Result<Vec<String>> {
+    let data = fs::read_to_string(path)?;
+
+    let patterns = data
+        .lines()
+        .filter(|l| !l.is_empty() && !l.trim_start().starts_with('#'))
+        .map(|l| l.to_string())
+        .collect();
+
+    Ok(patterns)
+}
+
+/// The short options that take a number, such as `-m NUM`.
+const NUMBER_SHORTS: &str = "ABCDISm";
+
+/// Get the short and long options of `app` that take a value, so that the next argument
+/// is not an option even if it looks like `-NUM`.
+fn value_options(app: &App) -> (String, Vec<String>) {
+    let mut shorts = String::new();
+    let mut longs = vec![];
+
+    for opt in app.p.opts.iter().filter(|o| o.b.is_set(ArgSettings::TakesValue)) {
+        shorts.extend(opt.s.short);
+        longs.extend(opt.s.long.map(|l| format!("--{}", l)));
+        for (alias, _) in opt.s.aliases.iter().flatten() {
+            longs.push(format!("--{}", alias));
+        }
+    }
+
+    (shorts, longs)
+}
+
+/// Rewrite the grep style options that clap can not parse with `AllowLeadingHyphen`.
+/// `-NUM` is `--context=NUM`, and a number attached to a short option is split (`-m1` is `-m 1`, `-nA2` is `-nA 2`).
+/// A number after the flags that take no value is `-NUM` (`-n2` is `-n --context=2`).
+fn normalize_args<I: IntoIterator<Item = OsString>>(app: &App, args: I) -> Vec<OsString> {
+    let (value_shorts, value_longs) = value_options(app);
+    let mut result: Vec<OsString> = vec![];
+    let mut is_value = false;
+    let mut is_positional = false;
+
+    for arg in args {
+        let text = match arg.to_str() {
+            Some(text) if !is_value && !is_positional && !result.is_empty() => text.to_string(),
+            _ => {
+                is_value = false;
+                result.push(arg);
+                continue;
+            }
+        };
+
+        if text == "--" {
+            is_positional = true;
+            result.push(arg);
+            continue;
+        }
+
+        let flags = match text.strip_prefix('-') {
+            Some(flags) if !flags.starts_with('-') => flags,
+            _ => {
+                is_value = value_longs.contains(&text);
+                result.push(arg);
+                continue;
+            }
+        };
+
+        let number_start = flags.find(|c: char| c.is_ascii_digit()).unwrap_or(flags.len());
+        let (names, number) = flags.split_at(number_start);
+        let is_number = !number.is_empty() && number.chars().all(|c| c.is_ascii_digit());
+
+        if is_number && names.is_empty() {
+            result.push(OsString::from(format!("--context={}", number)));
+        } else if is_number
+            && names.chars().all(|c| c.is_ascii_alphabetic())
+            && names.ends_with(|c| NUMBER_SHORTS.contains(c))
+        {
+            result.push(OsString::from(format!("-{}", names)));
+            result.push(OsString::from(number));
+        } else if is_number
+            && names.chars().all(|c| c.is_ascii_alphabetic() && !value_shorts.contains(c))
+        {
+            // the number after the flags is `-NUM`, as GNU grep reads `-l2` as `-l -2`.
+            result.push(OsString::from(format!("-{}", names)));
+            result.push(OsString::from(format!("--context={}", number)));
+        } else {
+            is_value = flags.chars().all(|c| c.is_ascii_alphabetic()) && flags.ends_with(|c| value_shorts.contains(c));
+            result.push(arg);
+        }
+    }
+
+    result
+}
+
+/// Exit with the message of `err`. Like grep, errors exit with 2.
+fn exit_with(err: clap::Error) -> ! {
+    match err.kind {
+        clap::ErrorKind::HelpDisplayed | clap::ErrorKind::VersionDisplayed => err.exit(),
+        _ => {
+            eprintln!("{}", err.message);
+            process::exit(2);
+        }
+    }
+}
+
+/// Parse a size like `10`, `4K`, `2M` or `1G` into bytes.
+fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (num, unit) = match size.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&size[..i], c.to_ascii_uppercase()),
+        _ => (size, 'B'),
+    };
+
+    let scale: u64 = match unit {
+        'B' => 1,
+        'K' => 1 << 10,
+        'M' => 1 << 20,
+        'G' => 1 << 30,
+        _ => return None,
+    };
+
+    num.parse::<u64>().ok()?.checked_mul(scale)
+}
+
+/// Parse a record delimiter with the escapes `\n`, `\r`, `\t`, `\0`, `\\` and `\xHH`.
+fn parse_delimiter(text: &str) -> Option<Vec<u8>> {
+    let mut result = vec![];
+
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            result.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        match chars.next()? {
+            'n' => result.push(b'\n'),
+            'r' => result.push(b'\r'),
+            't' => result.push(b'\t'),
+            '0' => result.push(b'\0'),
+            '\\' => result.push(b'\\'),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                result.push(u8::from_str_radix(&hex, 16).ok()?);
+            }
+            _ => return None,
+        }
+    }
+
+    if result.is_empty() {
+        return None;
+    }
+    Some(result)
+}
+
+/// Read paths from `path` (`-` is stdin), one per line.
+/// Empty lines are skipped.
+fn read_path_list(path: &str) -> io::Result<Vec<String>> {
+    let mut data = String::new();
+    if path == "-" {
+        io::stdin().read_to_string(&mut data)?;
+    } else {
+        data = fs::read_to_string(path)?;
+    }
+
+    let paths = data
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_string())
+        .collect();
+
+    Ok(paths)
+}
+
+/// Build the matcher of a single pattern.
+/// If the regex is too large to compile, fall back to the skeleton engine.
+fn build_matcher(
+    matches: &clap::ArgMatches,
+    homoglyphs: &Homoglyphs,
+    config: &mut greplib::Config,
+    text: &str,
+) -> HmgMatcher {
+    let is_elongation = matches.is_present("elongation");
+    let is_skeleton = matches.is_present("skeleton");
+    let is_approximate = matches.is_present("approximate")
+        || matches.is_present("max_errors")
+        || matches.is_present("best_match");
+
+    if is_approximate {
+        let costs = ApproxCosts {
+            delete: value_t!(matches, "delete_cost", u32).unwrap_or_else(|e| exit_with(e)),
+            insert: value_t!(matches, "insert_cost", u32).unwrap_or_else(|e| exit_with(e)),
+            substitute: value_t!(matches, "substitute_cost", u32).unwrap_or_else(|e| exit_with(e)),
+            homoglyph: value_t!(matches, "homoglyph_cost", u32).unwrap_or_else(|e| exit_with(e)),
+        };
+
+        let mut approx = ApproxMatcher::new(homoglyphs, text, costs);
+        approx.case_insensitive(matches.is_present("ignore_case"));
+
+        let max_errors = if matches.is_present("max_errors") {
+            value_t!(matches, "max_errors", u32).unwrap_or_else(|e| exit_with(e))
+        } else if matches.is_present("best_match") {
+            approx.worst_errors()
+        } else {
+            1
+        };
+        approx.max_errors(max_errors);
+
+        return HmgMatcher::Approx(approx);
+    }
+
+    if is_skeleton {
+        return HmgMatcher::Skeleton(SkeletonMatcher::new(homoglyphs, text));
+    }
+
+    // Get regex pattern
+    let regex_pattern = homoglyphs.get_pattern_regex(text);
+
+    match config.build_matcher(&regex_pattern) {
+        Ok(m) => HmgMatcher::Regex(m),
+        Err(ref e) if !is_elongation && greplib::Config::is_size_limit_error(&**e) => {
+            HmgMatcher::Skeleton(SkeletonMatcher::new(homoglyphs, text))
+        }
+        Err(e) => {
+            eprintln!("hmgrep: {}", e);
+            process::exit(2);
+        }
+    }
+}
+
 ///
 fn main() {
     // Get command args matches
-    let matches = build_app().get_matches();
+    let app = build_app();
+    let args = normalize_args(&app, env::args_os());
+    let matches = app.get_matches_from_safe(args).unwrap_or_else(|e| exit_with(e));
+
+    // Get args `PATTERNS` and `PATH`.
+    // If patterns are given by -e or -f, the first positional arg is a PATH.
+    let mut paths: Vec<String> = match matches.values_of("PATH") {
+        Some(values) => values.map(|v| v.to_string()).collect(),
+        None => vec![],
+    };
+
+    let mut patterns: Vec<String> = vec![];
+    let mut blocklist: Option<Vec<String>> = None;
+    if let Some(f) = matches.value_of("blocklist") {
+        match read_pattern_file(f) {
+            Ok(entries) => blocklist = Some(entries),
+            Err(e) => {
+                eprintln!("hmgrep: {}: {}", f, e);
+                process::exit(2);
+            }
+        }
+    }
+    if let Some(values) = matches.values_of("regexp") {
+        patterns.extend(values.map(|v| v.to_string()));
+    }
+    if let Some(files) = matches.values_of("file") {
+        for f in files {
+            match read_pattern_file(f) {
+                Ok(mut p) => patterns.append(&mut p),
+                Err(e) => {
+                    eprintln!("hmgrep: {}: {}", f, e);
+                    process::exit(2);
+                }
+            }
+        }
+    }
+
+    let is_pattern_option = matches.is_present("regexp")
+        || matches.is_present("file")
+        || matches.is_present("blocklist");
+    if !is_pattern_option {
+        patterns.push(matches.value_of("PATTERNS").unwrap().to_string());
+    } else if let Some(p) = matches.value_of("PATTERNS") {
+        paths.insert(0, p.to_string());
+    }
+
+    if let Some(f) = matches.value_of("files_from") {
+        match read_path_list(f) {
+            Ok(mut p) => paths.append(&mut p),
+            Err(e) => {
+                eprintln!("hmgrep: {}: {}", f, e);
+                process::exit(2);
+            }
+        }
+    }
+
+    // without PATH, read stdin.
+    if paths.is_empty() && !matches.is_present("files_from") {
+        paths.push("-".to_string());
+    }
+    let path_list: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+
+    // Print file names if more than one file can be searched, like grep.
+    let with_filename = if matches.is_present("with_filename") {
+        true
+    } else if matches.is_present("no_filename") {
+        false
+    } else {
+        path_list.len() > 1 || path_list.iter().any(|p| p.is_dir())
+    };
 
-    // Get arg `PATTERNS`
-    let text = matches.value_of("PATTERNS");
-    let paths = matches.values_of("PATH");
-    let path_list = PathBuf::from(paths.unwrap().next().unwrap());
+    // Get walker options
+    let include: Vec<String> = match matches.values_of("include") {
+        Some(values) => values.map(|v| v.to_string()).collect(),
+        None => vec![],
+    };
+    let mut exclude: Vec<String> = match matches.values_of("exclude") {
+        Some(values) => values.map(|v| v.to_string()).collect(),
+        None => vec![],
+    };
+    if let Some(files) = matches.values_of("exclude_from") {
+        for f in files {
+            match read_pattern_file(f) {
+                Ok(mut globs) => exclude.append(&mut globs),
+                Err(e) => {
+                    eprintln!("hmgrep: {}: {}", f, e);
+                    process::exit(2);
+                }
+            }
+        }
+    }
+    let exclude_dir: Vec<String> = match matches.values_of("exclude_dir") {
+        Some(values) => values.map(|v| v.to_string()).collect(),
+        None => vec![],
+    };
+    let delimiter = matches.value_of("delimiter").map(|text| match parse_delimiter(text) {
+        Some(delimiter) => delimiter,
+        None => {
+            eprintln!("hmgrep: invalid --delimiter: {}", text);
+            process::exit(2);
+        }
+    });
+    let is_follow = matches.is_present("follow") || matches.is_present("dereference_recursive");
+
+    let sort_mode = match matches.value_of("sort") {
+        Some("path") => SortMode::Path,
+        Some("modified") => SortMode::Modified,
+        Some("none") => SortMode::None,
+        _ => SortMode::Walk,
+    };
+
+    // Get color options. Colors are read from `GREP_COLORS` (`ms`, `fn` and `ln`), then `--colors`.
+    let color_mode = match matches.value_of("color") {
+        Some("always") => ColorMode::Always,
+        Some("never") => ColorMode::None,
+        _ => ColorMode::Auto,
+    };
+    let grep_colors = env::var("GREP_COLORS").ok();
+    let user_colors: Vec<&str> = match matches.values_of("colors") {
+        Some(values) => values.collect(),
+        None => vec![],
+    };
+    let colors = match greplib::color::build_colors(grep_colors.as_deref(), &user_colors) {
+        Ok(colors) => colors,
+        Err(e) => {
+            eprintln!("hmgrep: {}", e);
+            process::exit(2);
+        }
+    };
 
     // Get Homoglyphs options
     let is_japanese_kana = matches.is_present("japanese_kana");
     let is_cjk_width = matches.is_present("cjk_width");
+    let is_elongation = matches.is_present("elongation");
 
     // Get Grep options
     let ignore_case = matches.is_present("ignore_case");
-    // let mut max_count = value_t!(matches, "max_count", u64).unwrap_or_else(|e| e.exit());
+    let invert_match = matches.is_present("invert_match");
     let only_matching = matches.is_present("only_matching");
-    let line_number = matches.is_present("line_number");
-    let before_context = value_t!(matches, "before_context", usize).unwrap_or_else(|e| e.exit());
-    let after_context = value_t!(matches, "after_context", usize).unwrap_or_else(|e| e.exit());
+    let format = match matches.value_of("format") {
+        Some(f) => f,
+        None if matches.is_present("json") => "json",
+        None => "text",
+    };
+
+    // the structured formats always have the line numbers.
+    let line_number = matches.is_present("line_number") || format != "text";
+
+    let output_mode = if format == "json" {
+        OutputMode::Json
+    } else if format == "sarif" {
+        OutputMode::Sarif
+    } else if matches.is_present("quiet") {
+        OutputMode::Quiet
+    } else if matches.is_present("files_with_matches") {
+        OutputMode::FilesWithMatches
+    } else if matches.is_present("files_without_match") {
+        OutputMode::FilesWithoutMatch
+    } else if matches.is_present("count") {
+        OutputMode::Count
+    } else {
+        OutputMode::Lines
+    };
+    // -A and -B take priority over -C.
+    let context = match matches.value_of("context") {
+        Some(_) => value_t!(matches, "context", usize).unwrap_or_else(|e| exit_with(e)),
+        None => 0,
+    };
+    let before_context = match matches.value_of("before_context") {
+        Some(_) => value_t!(matches, "before_context", usize).unwrap_or_else(|e| exit_with(e)),
+        None => context,
+    };
+    let after_context = match matches.value_of("after_context") {
+        Some(_) => value_t!(matches, "after_context", usize).unwrap_or_else(|e| exit_with(e)),
+        None => context,
+    };
 
     // Get Homoglyphs object
-    let pattern = Homoglyphs::new()
+    let mut homoglyphs = Homoglyphs::new();
+    homoglyphs
+        .is_ignore_case(ignore_case)
         .with_cjk_width(is_cjk_width)
         .with_japanese_kana(is_japanese_kana)
-        .get_pattern(text.unwrap());
+        .with_elongation(is_elongation)
+        .with_line_break(matches.is_present("multiline"));
 
-    // Get regex pattern
-    let regex_pattern = hmglib::generate_pattern_regex(pattern);
+    if matches.is_present("max_elongation") {
+        let max_elongation =
+            value_t!(matches, "max_elongation", usize).unwrap_or_else(|e| exit_with(e));
+        if max_elongation < 1 {
+            let message = "The value of --max-elongation must be 1 or more";
+            exit_with(clap::Error::with_description(message, clap::ErrorKind::InvalidValue));
+        }
+        homoglyphs.max_elongation(max_elongation);
+    }
+
+    // Get the finder of substituted characters, to highlight and explain them.
+    let explainer = Explainer::new(&homoglyphs, blocklist.as_ref().unwrap_or(&patterns));
 
     // Get grep
-    let config = greplib::Config::new()
+    let mut config = greplib::Config::new()
         .after_context(after_context)
         .before_context(before_context)
+        .passthru(matches.is_present("passthru"))
+        .with_context(["context", "before_context", "after_context"].iter().any(|a| matches.is_present(a)))
+        .no_group_separator(matches.is_present("no_group_separator"))
         .only_matching(only_matching)
         .case_insensitive(ignore_case)
         .line_number(line_number)
+        .invert_match(invert_match)
+        .with_filename(with_filename)
+        .output_mode(output_mode)
+        .sort(sort_mode)
+        .color(color_mode)
+        .colors(&colors)
+        .explainer(&explainer)
+        .explain(matches.is_present("explain"))
+        .include(&include)
+        .exclude(&exclude)
+        .exclude_dir(&exclude_dir)
+        .hidden(matches.is_present("hidden"))
+        .no_ignore(matches.is_present("no_ignore"))
+        .search_zip(matches.is_present("search_zip"))
+        .documents(matches.is_present("documents"))
+        .mail(matches.is_present("mail"))
+        .null_data(matches.is_present("null_data"))
+        .multi_line(matches.is_present("multiline"))
+        .follow(is_follow)
         .build();
 
-    greplib::scan(config, &regex_pattern, vec![path_list]);
+    if matches.is_present("max_count") {
+        let max_count = value_t!(matches, "max_count", u64).unwrap_or_else(|e| exit_with(e));
+        config.max_matches(max_count);
+    }
+
+    if matches.is_present("max_depth") {
+        let max_depth = value_t!(matches, "max_depth", usize).unwrap_or_else(|e| exit_with(e));
+        config.max_depth(max_depth);
+    }
+
+    if let Some(size) = matches.value_of("max_filesize") {
+        match parse_size(size) {
+            Some(bytes) => config.max_filesize(bytes),
+            None => {
+                eprintln!("hmgrep: invalid --max-filesize: {}", size);
+                process::exit(2);
+            }
+        };
+    }
+
+    let max_member_size = matches.value_of("max_member_size").unwrap();
+    match parse_size(max_member_size) {
+        Some(bytes) => config.max_member_size(bytes),
+        None => {
+            eprintln!("hmgrep: invalid --max-member-size: {}", max_member_size);
+            process::exit(2);
+        }
+    };
+    let max_archive_depth = value_t!(matches, "max_archive_depth", usize).unwrap_or_else(|e| exit_with(e));
+    config.max_archive_depth(max_archive_depth);
+
+    if let Some(delimiter) = &delimiter {
+        config.delimiter(delimiter);
+    }
+
+    if let Some(separator) = matches.value_of("group_separator") {
+        config.group_separator(separator.as_bytes());
+    }
+
+    // `none` searches the raw bytes, `auto` detects UTF-16 by BOM.
+    match matches.value_of("encoding") {
+        Some("none") => {}
+        Some("auto") | None => {
+            config.bom_sniffing(true);
+        }
+        Some(label) => {
+            config.bom_sniffing(true).encoding(label);
+        }
+    };
+    if let Err(e) = config.build_encoding() {
+        eprintln!("hmgrep: {}", e);
+        process::exit(2);
+    }
+
+    // check the globs before searching.
+    if let Err(e) = config.build_overrides() {
+        eprintln!("hmgrep: {}", e);
+        process::exit(2);
+    }
+
+    // Get matcher. With multiple patterns, the output shows which pattern matched.
+    let mut matcher = if let Some(entries) = blocklist {
+        HmgMatcher::Blocklist(Blocklist::new(&homoglyphs, entries))
+    } else if patterns.len() == 1 {
+        build_matcher(&matches, &homoglyphs, &mut config, &patterns[0])
+    } else {
+        let pattern_list = patterns
+            .iter()
+            .map(|p| Pattern {
+                label: p.clone(),
+                matcher: build_matcher(&matches, &homoglyphs, &mut config, p),
+            })
+            .collect();
+        HmgMatcher::Patterns(pattern_list)
+    };
+
+    // with --decode, the matcher is applied to the decoded lines, fields or cells.
+    if let Some(values) = matches.values_of("decode") {
+        let decodings = values.filter_map(Decoding::parse).collect();
+        matcher = HmgMatcher::Decode(DecodeMatcher::new(matcher, decodings));
+    }
+
+    // with --json-field, the matcher is applied to the decoded fields.
+    if let Some(values) = matches.values_of("json_field") {
+        let mut fields = vec![];
+        for value in values {
+            match FieldPath::parse(value) {
+                Ok(field) => fields.push(field),
+                Err(e) => {
+                    eprintln!("hmgrep: {}", e);
+                    process::exit(2);
+                }
+            }
+        }
+        matcher = HmgMatcher::JsonField(JsonFieldMatcher::new(matcher, fields));
+    }
+
+    // with --csv, the matcher is applied to the columns of each record.
+    if matches.is_present("csv") || matches.is_present("tsv") || matches.is_present("column") {
+        let columns = match matches.values_of("column") {
+            Some(values) => values.map(Column::parse).collect(),
+            None => vec![],
+        };
+        let has_header = !matches.is_present("no_header");
+        matcher = HmgMatcher::CsvColumn(CsvColumnMatcher::new(matcher, columns, has_header, matches.is_present("tsv")));
+    }
+
+    // with -b, search twice. first to get the lowest error count.
+    if matches.is_present("best_match") {
+        // stdin can not be read twice.
+        if path_list.iter().any(|p| p.as_path() == Path::new("-")) {
+            eprintln!("hmgrep: --best-match can not search stdin");
+            process::exit(2);
+        }
+
+        match greplib::approx::best_errors(config, &matcher, path_list.clone()) {
+            Some(best) => matcher.max_errors(best),
+            None => process::exit(1),
+        };
+    }
+
+    // exit status is same as grep. 0: matched, 1: not matched, 2: error.
+    let status = greplib::scan(config, matcher, path_list);
+    process::exit(status.exit_code(output_mode == OutputMode::Quiet));
 }
@@ -0,0 +1,60 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+// macro crate
+#[macro_use]
+extern crate clap;
+extern crate hmg_utils;
+
+// modules
+use clap::{App, AppSettings, Arg};
+use std::path::PathBuf;
+use std::process;
+
+// local modules
+use hmg_utils::greplib;
+
+use greplib::hazard::{scan_hazards, HazardMatcher};
+
+/// Parse args and options function.
+fn build_app() -> clap::App<'static, 'static> {
+    App::new("hmscan")
+        .about("Report Unicode security hazards (bidi controls, invisible characters, tag characters, mixed scripts).")
+        .version(crate_version!())
+        .author(crate_authors!())
+        .setting(AppSettings::DeriveDisplayOrder)
+        // -- OPTIONS --
+        // --no-mixed-script
+        .arg(
+            Arg::with_name("no_mixed_script")
+                .help("Do not report words that mix scripts.")
+                .long("no-mixed-script"),
+        )
+        // -- PATH --
+        .arg(
+            Arg::with_name("PATH")
+                .allow_hyphen_values(true)
+                .multiple(true)
+                .required(true),
+        )
+}
+
+/// Scan every PATH and print the risky characters found.
+fn main() {
+    // Get command args matches
+    let matches = build_app().get_matches();
+
+    // Get arg `PATH`
+    let path_list: Vec<PathBuf> = matches.values_of("PATH").unwrap().map(PathBuf::from).collect();
+
+    // Get scan options
+    let is_mixed_script = !matches.is_present("no_mixed_script");
+
+    let config = greplib::Config::new().line_number(true).build();
+    let matcher = HazardMatcher::new(is_mixed_script);
+
+    // exit status is same as grep. 0: found, 1: not found, 2: error.
+    let status = scan_hazards(config, matcher, path_list);
+    process::exit(status.exit_code(false));
+}
@@ -0,0 +1,40 @@
+// Copyright (c) 2022 Blacknon. All rights reserved.
+// Use of this source code is governed by an MIT license
+// that can be found in the LICENSE file.
+
+//! Homoglyphs utils.
+//!
+//! - `hmglib` ... generate homoglyph patterns and confusable skeletons.
+//! - `greplib` ... search files with the patterns (used by `hmgrep` and `hmscan`).
+
+extern crate aho_corasick;
+extern crate bzip2;
+extern crate crossbeam;
+extern crate csv;
+extern crate encoding_rs;
+extern crate flate2;
+extern crate grep;
+extern crate grep_matcher;
+extern crate grep_regex;
+extern crate grep_searcher;
+extern crate ignore;
+extern crate itertools;
+extern crate kana;
+extern crate lazy_static;
+extern crate lzma_rs;
+extern crate mail_parser;
+extern crate memchr;
+extern crate num_cpus;
+extern crate quick_xml;
+extern crate regex;
+extern crate ruzstd;
+extern crate serde;
+extern crate serde_json;
+extern crate tar;
+extern crate termcolor;
+extern crate unicode_names2;
+extern crate unicode_script;
+extern crate zip;
+
+pub mod greplib;
+pub mod hmglib;